@@ -0,0 +1,112 @@
+use base64::Engine;
+use redis::{FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Value};
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// a binary safe redis string
+// the utf-8 text is sent as a plain string, the other bytes as {type, encoding, value} with base64
+// both forms are accepted from the payload
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Binary(pub Vec<u8>);
+
+// serialize the bytes as the text, or the base64 if they are not utf-8
+pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match std::str::from_utf8(bytes) {
+        Ok(s) => serializer.serialize_str(s),
+        Err(_) => {
+            let mut s = serializer.serialize_struct("binary", 3)?;
+            s.serialize_field("type", "binary")?;
+            s.serialize_field("encoding", "base64")?;
+            s.serialize_field(
+                "value",
+                &base64::engine::general_purpose::STANDARD.encode(bytes),
+            )?;
+            s.end()
+        }
+    }
+}
+
+impl Serialize for Binary {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Payload {
+    Text(String),
+    Encoded {
+        encoding: Option<String>,
+        value: String,
+    },
+}
+
+impl<'de> Deserialize<'de> for Binary {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Payload::deserialize(deserializer)? {
+            Payload::Text(s) => Ok(Binary(s.into_bytes())),
+            Payload::Encoded { encoding, value } => match encoding.as_deref() {
+                None | Some("base64") => base64::engine::general_purpose::STANDARD
+                    .decode(value)
+                    .map(Binary)
+                    .map_err(D::Error::custom),
+                Some("utf8") | Some("text") => Ok(Binary(value.into_bytes())),
+                Some(e) => Err(D::Error::custom(format!("unknown encoding {}", e))),
+            },
+        }
+    }
+}
+
+impl FromRedisValue for Binary {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        match v {
+            Value::SimpleString(s) => Ok(Binary(s.clone().into_bytes())),
+            Value::Okay => Ok(Binary(b"OK".to_vec())),
+            _ => Ok(Binary(Vec::from_redis_value(v)?)),
+        }
+    }
+}
+
+impl ToRedisArgs for Binary {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_arg(&self.0)
+    }
+}
+
+impl From<&str> for Binary {
+    fn from(s: &str) -> Self {
+        Binary(s.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Binary {
+    fn from(s: String) -> Self {
+        Binary(s.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for Binary {
+    fn from(v: Vec<u8>) -> Self {
+        Binary(v)
+    }
+}
+
+impl PartialEq<&str> for Binary {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
@@ -0,0 +1,193 @@
+use super::Compression;
+use crate::err::CusError;
+use std::io::{Read, Write};
+
+fn err(e: impl ToString) -> CusError {
+    CusError::App(e.to_string())
+}
+
+// the decompressed value is limited, a small bomb would run out of the memory
+const MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+fn too_large() -> CusError {
+    CusError::App(format!(
+        "The decompressed value is larger than {} MB",
+        MAX_SIZE / 1024 / 1024
+    ))
+}
+
+fn read_all(r: impl Read) -> Result<Vec<u8>, CusError> {
+    let mut out = vec![];
+    r.take(MAX_SIZE + 1).read_to_end(&mut out)?;
+    if out.len() as u64 > MAX_SIZE {
+        return Err(too_large());
+    }
+    Ok(out)
+}
+
+pub struct Gzip;
+
+impl Compression for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0x1f, 0x8b])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        read_all(flate2::read::GzDecoder::new(bytes))
+    }
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        let mut e = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        e.write_all(bytes)?;
+        Ok(e.finish()?)
+    }
+}
+
+pub struct Zstd;
+
+impl Compression for Zstd {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        read_all(zstd::stream::read::Decoder::new(bytes)?)
+    }
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        Ok(zstd::stream::encode_all(bytes, 0)?)
+    }
+}
+
+// the lz4 frame format, the block format has no magic number
+pub struct Lz4;
+
+impl Compression for Lz4 {
+    fn name(&self) -> &'static str {
+        "lz4"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0x04, 0x22, 0x4d, 0x18])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        read_all(lz4_flex::frame::FrameDecoder::new(bytes))
+    }
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        let mut e = lz4_flex::frame::FrameEncoder::new(vec![]);
+        e.write_all(bytes)?;
+        e.finish().map_err(err)
+    }
+}
+
+// the snappy framing format
+pub struct SnappyFrame;
+
+impl Compression for SnappyFrame {
+    fn name(&self) -> &'static str {
+        "snappy-frame"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(b"\xff\x06\x00\x00sNaPpY")
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        read_all(snap::read::FrameDecoder::new(bytes))
+    }
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        let mut e = snap::write::FrameEncoder::new(vec![]);
+        e.write_all(bytes)?;
+        e.into_inner().map_err(err)
+    }
+}
+
+// the raw snappy block, as most clients write it
+pub struct Snappy;
+
+impl Compression for Snappy {
+    fn name(&self) -> &'static str {
+        "snappy"
+    }
+    // there is no magic number, the value is only taken as snappy if it decompresses to text
+    fn detect(&self, bytes: &[u8]) -> bool {
+        std::str::from_utf8(bytes).is_err()
+            && self
+                .decode(bytes)
+                .is_ok_and(|v| std::str::from_utf8(&v).is_ok())
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        // the length is in the header, it is checked before the buffer is allocated
+        if snap::raw::decompress_len(bytes).map_err(err)? as u64 > MAX_SIZE {
+            return Err(too_large());
+        }
+        snap::raw::Decoder::new().decompress_vec(bytes).map_err(err)
+    }
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError> {
+        snap::raw::Encoder::new().compress_vec(bytes).map_err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"hello hello hello hello hello hello hello hello";
+
+    fn round_trip(c: &dyn Compression) -> Vec<u8> {
+        let b = c.encode(TEXT).unwrap();
+        assert_eq!(c.decode(&b).unwrap(), TEXT);
+        b
+    }
+
+    #[test]
+    fn framed() {
+        let codecs: [&dyn Compression; 4] = [&Gzip, &Zstd, &Lz4, &SnappyFrame];
+        for c in codecs {
+            let b = round_trip(c);
+            assert!(c.detect(&b), "{}", c.name());
+            assert!(!c.detect(TEXT), "{}", c.name());
+        }
+    }
+
+    #[test]
+    fn snappy() {
+        let b = round_trip(&Snappy);
+        assert!(b.len() < TEXT.len());
+        // the text itself is never taken as snappy
+        assert!(!Snappy.detect(TEXT));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Gzip.decode(&[0x1f, 0x8b, 0x08, 0x00, 0x01]).is_err());
+        assert!(Zstd.decode(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]).is_err());
+        assert!(Lz4.decode(&[0x04, 0x22, 0x4d, 0x18, 0x00]).is_err());
+        assert!(SnappyFrame.decode(b"\xff\x06\x00\x00sNaPpY\x01").is_err());
+        assert!(Snappy.decode(&[0xff, 0xff, 0xff]).is_err());
+        let mut b = Gzip.encode(TEXT).unwrap();
+        b.truncate(b.len() - 4);
+        assert!(Gzip.decode(&b).is_err());
+    }
+
+    #[test]
+    fn size_limit() {
+        let zeros = vec![0; MAX_SIZE as usize + 1];
+        // gzip is read by the same read_all
+        let codecs: [&dyn Compression; 3] = [&Zstd, &Lz4, &SnappyFrame];
+        for c in codecs {
+            let b = c.encode(&zeros).unwrap();
+            assert!(c.decode(&b).is_err(), "{}", c.name());
+            let b = c.encode(&zeros[1..]).unwrap();
+            assert_eq!(
+                c.decode(&b).unwrap().len(),
+                MAX_SIZE as usize,
+                "{}",
+                c.name()
+            );
+        }
+        // the snappy header says 256 MB, nothing is allocated
+        assert!(Snappy
+            .decode(&[0x80, 0x80, 0x80, 0x80, 0x01, 0x00])
+            .is_err());
+    }
+}
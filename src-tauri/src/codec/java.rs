@@ -0,0 +1,541 @@
+use super::{binary, Serialization};
+use crate::err::CusError;
+use serde_json::{Map, Number, Value};
+use std::rc::Rc;
+
+// the java object serialization stream, it is only decoded
+pub struct Java;
+
+impl Serialization for Java {
+    fn name(&self) -> &'static str {
+        "java"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0xac, 0xed, 0x00, 0x05])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        Stream::new(bytes).read_all()
+    }
+}
+
+const TC_NULL: u8 = 0x70;
+const TC_REFERENCE: u8 = 0x71;
+const TC_CLASSDESC: u8 = 0x72;
+const TC_OBJECT: u8 = 0x73;
+const TC_STRING: u8 = 0x74;
+const TC_ARRAY: u8 = 0x75;
+const TC_CLASS: u8 = 0x76;
+const TC_BLOCKDATA: u8 = 0x77;
+const TC_ENDBLOCKDATA: u8 = 0x78;
+const TC_RESET: u8 = 0x79;
+const TC_BLOCKDATALONG: u8 = 0x7a;
+const TC_EXCEPTION: u8 = 0x7b;
+const TC_LONGSTRING: u8 = 0x7c;
+const TC_PROXYCLASSDESC: u8 = 0x7d;
+const TC_ENUM: u8 = 0x7e;
+
+const BASE_HANDLE: u32 = 0x7e0000;
+
+// the nested objects and class descriptors, a deeper stream is rejected before the stack runs out
+const MAX_DEPTH: usize = 100;
+
+const SC_WRITE_METHOD: u8 = 0x01;
+const SC_SERIALIZABLE: u8 = 0x02;
+const SC_EXTERNALIZABLE: u8 = 0x04;
+const SC_BLOCK_DATA: u8 = 0x08;
+
+struct Field {
+    types: u8,
+    name: String,
+}
+
+struct ClassDesc {
+    name: String,
+    flags: u8,
+    fields: Vec<Field>,
+    parent: Option<Rc<ClassDesc>>,
+}
+
+// a reference to a value is shown as its handle, the value is not copied
+enum Handle {
+    Class(Rc<ClassDesc>),
+    Value,
+}
+
+struct Stream<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    handles: Vec<Handle>,
+    depth: usize,
+}
+
+fn invalid(s: &str) -> CusError {
+    CusError::App(format!("Invalid java serialization: {}", s))
+}
+
+impl<'a> Stream<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            handles: vec![],
+            depth: 0,
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CusError> {
+        let end = self.pos.checked_add(n).ok_or_else(|| invalid("length"))?;
+        let b = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| invalid("unexpected end"))?;
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], CusError> {
+        let mut a = [0; N];
+        a.copy_from_slice(self.take(N)?);
+        Ok(a)
+    }
+
+    fn u8(&mut self) -> Result<u8, CusError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn peek(&self) -> Result<u8, CusError> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| invalid("unexpected end"))
+    }
+
+    fn u16(&mut self) -> Result<u16, CusError> {
+        Ok(u16::from_be_bytes(self.take_array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, CusError> {
+        Ok(i32::from_be_bytes(self.take_array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, CusError> {
+        Ok(i64::from_be_bytes(self.take_array()?))
+    }
+
+    // the modified utf-8, the null and the surrogate pairs are not exactly utf-8
+    fn utf(&mut self, len: usize) -> Result<String, CusError> {
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn new_handle(&mut self, h: Handle) -> usize {
+        self.handles.push(h);
+        self.handles.len() - 1
+    }
+
+    // run the nested read, counting the depth
+    fn nested<T>(&mut self, read: fn(&mut Self) -> Result<T, CusError>) -> Result<T, CusError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid("too deeply nested"));
+        }
+        self.depth += 1;
+        let r = read(self);
+        self.depth -= 1;
+        r
+    }
+
+    fn read_all(&mut self) -> Result<Value, CusError> {
+        if self.take(4)? != [0xac, 0xed, 0x00, 0x05] {
+            return Err(invalid("magic"));
+        }
+        let mut contents = vec![];
+        while self.pos < self.bytes.len() {
+            if self.peek()? == TC_RESET {
+                self.pos += 1;
+                self.handles.clear();
+                continue;
+            }
+            contents.push(self.content()?);
+        }
+        match contents.len() {
+            1 => Ok(contents.remove(0)),
+            _ => Ok(Value::Array(contents)),
+        }
+    }
+
+    // the object or the block data
+    fn content(&mut self) -> Result<Value, CusError> {
+        match self.peek()? {
+            TC_BLOCKDATA => {
+                self.pos += 1;
+                let len = self.u8()? as usize;
+                Ok(binary(self.take(len)?))
+            }
+            TC_BLOCKDATALONG => {
+                self.pos += 1;
+                let len = self.i32()? as usize;
+                Ok(binary(self.take(len)?))
+            }
+            _ => self.object(),
+        }
+    }
+
+    // the contents until the end block data
+    // the handle is gone if the stream is reset before the class descriptor ends
+    fn set_handle(&mut self, handle: usize, value: Handle) -> Result<(), CusError> {
+        *self
+            .handles
+            .get_mut(handle)
+            .ok_or_else(|| invalid("reset"))? = value;
+        Ok(())
+    }
+
+    fn annotation(&mut self) -> Result<Vec<Value>, CusError> {
+        let mut values = vec![];
+        loop {
+            match self.peek()? {
+                TC_ENDBLOCKDATA => {
+                    self.pos += 1;
+                    return Ok(values);
+                }
+                TC_RESET => {
+                    self.pos += 1;
+                    self.handles.clear();
+                }
+                _ => values.push(self.content()?),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, CusError> {
+        self.nested(Self::read_object)
+    }
+
+    fn read_object(&mut self) -> Result<Value, CusError> {
+        match self.u8()? {
+            TC_NULL => Ok(Value::Null),
+            TC_REFERENCE => match self.reference()? {
+                (handle, Handle::Value) => Ok(handle_ref(handle)),
+                (_, Handle::Class(c)) => Ok(serde_json::json!({ "$class": c.name })),
+            },
+            TC_STRING => {
+                let len = self.u16()? as usize;
+                self.string(len)
+            }
+            TC_LONGSTRING => {
+                let len = self.i64()? as usize;
+                self.string(len)
+            }
+            TC_CLASSDESC | TC_PROXYCLASSDESC => {
+                self.pos -= 1;
+                let c = self.class_desc()?;
+                Ok(serde_json::json!({ "$class": c.map(|c| c.name.clone()) }))
+            }
+            TC_CLASS => {
+                let c = self.class_desc()?;
+                self.new_handle(Handle::Value);
+                Ok(serde_json::json!({ "$class": c.map(|c| c.name.clone()) }))
+            }
+            TC_ENUM => {
+                let c = self.class_desc()?.ok_or_else(|| invalid("enum class"))?;
+                self.new_handle(Handle::Value);
+                let constant = self.object()?;
+                Ok(serde_json::json!({ "$class": c.name, "$enum": constant }))
+            }
+            TC_ARRAY => self.array(),
+            TC_OBJECT => self.new_object(),
+            TC_EXCEPTION => Err(invalid("the stream has an exception")),
+            _ => Err(invalid("type code")),
+        }
+    }
+
+    fn reference(&mut self) -> Result<(u32, &Handle), CusError> {
+        let handle = self.i32()? as u32;
+        handle
+            .checked_sub(BASE_HANDLE)
+            .and_then(|i| self.handles.get(i as usize))
+            .map(|h| (handle, h))
+            .ok_or_else(|| invalid("reference"))
+    }
+
+    fn string(&mut self, len: usize) -> Result<Value, CusError> {
+        self.new_handle(Handle::Value);
+        Ok(Value::String(self.utf(len)?))
+    }
+
+    fn class_desc(&mut self) -> Result<Option<Rc<ClassDesc>>, CusError> {
+        self.nested(Self::read_class_desc)
+    }
+
+    fn read_class_desc(&mut self) -> Result<Option<Rc<ClassDesc>>, CusError> {
+        match self.u8()? {
+            TC_NULL => Ok(None),
+            TC_REFERENCE => match self.reference()? {
+                (_, Handle::Class(c)) => Ok(Some(c.clone())),
+                (_, Handle::Value) => Err(invalid("class reference")),
+            },
+            TC_CLASSDESC => {
+                let len = self.u16()? as usize;
+                let name = self.utf(len)?;
+                // the serial version uid
+                self.take(8)?;
+                let handle = self.new_handle(Handle::Value);
+                let flags = self.u8()?;
+                let count = self.u16()?;
+                let mut fields = vec![];
+                for _ in 0..count {
+                    let types = self.u8()?;
+                    let len = self.u16()? as usize;
+                    let name = self.utf(len)?;
+                    if types == b'L' || types == b'[' {
+                        // the class name of the field
+                        self.object()?;
+                    }
+                    fields.push(Field { types, name })
+                }
+                self.annotation()?;
+                let parent = self.class_desc()?;
+                let c = Rc::new(ClassDesc {
+                    name,
+                    flags,
+                    fields,
+                    parent,
+                });
+                self.set_handle(handle, Handle::Class(c.clone()))?;
+                Ok(Some(c))
+            }
+            TC_PROXYCLASSDESC => {
+                let handle = self.new_handle(Handle::Value);
+                let count = self.i32()?;
+                let mut interfaces = vec![];
+                for _ in 0..count {
+                    let len = self.u16()? as usize;
+                    interfaces.push(self.utf(len)?);
+                }
+                self.annotation()?;
+                let parent = self.class_desc()?;
+                let c = Rc::new(ClassDesc {
+                    name: format!("$Proxy({})", interfaces.join(",")),
+                    flags: SC_SERIALIZABLE,
+                    fields: vec![],
+                    parent,
+                });
+                self.set_handle(handle, Handle::Class(c.clone()))?;
+                Ok(Some(c))
+            }
+            _ => Err(invalid("class descriptor")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, CusError> {
+        let c = self.class_desc()?.ok_or_else(|| invalid("array class"))?;
+        self.new_handle(Handle::Value);
+        let len = self.i32()?;
+        if len < 0 {
+            return Err(invalid("array length"));
+        }
+        let types = c.name.as_bytes().get(1).copied().unwrap_or(b'L');
+        if types == b'B' {
+            return Ok(binary(self.take(len as usize)?));
+        }
+        let mut items = vec![];
+        for _ in 0..len {
+            items.push(self.field_value(types)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn new_object(&mut self) -> Result<Value, CusError> {
+        let c = self.class_desc()?.ok_or_else(|| invalid("object class"))?;
+        self.new_handle(Handle::Value);
+        let mut chain = vec![];
+        let mut current = Some(c.clone());
+        while let Some(c) = current {
+            current = c.parent.clone();
+            chain.push(c);
+        }
+        let mut map = Map::new();
+        map.insert("$class".into(), Value::String(c.name.clone()));
+        let mut annotations = vec![];
+        // the data of the super class is first
+        for c in chain.iter().rev() {
+            if c.flags & SC_EXTERNALIZABLE != 0 {
+                if c.flags & SC_BLOCK_DATA == 0 {
+                    return Err(invalid("externalizable of the protocol 1"));
+                }
+                annotations.append(&mut self.annotation()?);
+                continue;
+            }
+            if c.flags & SC_SERIALIZABLE == 0 {
+                continue;
+            }
+            for f in &c.fields {
+                let v = self.field_value(f.types)?;
+                map.insert(f.name.clone(), v);
+            }
+            if c.flags & SC_WRITE_METHOD != 0 {
+                annotations.append(&mut self.annotation()?);
+            }
+        }
+        if !annotations.is_empty() {
+            map.insert("$annotations".into(), Value::Array(annotations));
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn field_value(&mut self, types: u8) -> Result<Value, CusError> {
+        Ok(match types {
+            b'B' => Value::from(self.u8()? as i8),
+            b'C' => Value::String(
+                char::from_u32(self.u16()? as u32)
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            b'D' => float(f64::from_be_bytes(self.take_array()?)),
+            b'F' => float(f32::from_be_bytes(self.take_array()?) as f64),
+            b'I' => Value::from(self.i32()?),
+            b'J' => Value::from(self.i64()?),
+            b'S' => Value::from(i16::from_be_bytes(self.take_array()?)),
+            b'Z' => Value::Bool(self.u8()? != 0),
+            b'L' | b'[' => self.object()?,
+            _ => return Err(invalid("field type")),
+        })
+    }
+}
+
+fn handle_ref(handle: u32) -> Value {
+    serde_json::json!({ "$ref": format!("0x{:x}", handle) })
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stream(contents: &[&[u8]]) -> Vec<u8> {
+        let mut b = vec![0xac, 0xed, 0x00, 0x05];
+        for c in contents {
+            b.extend_from_slice(c);
+        }
+        b
+    }
+
+    fn utf(s: &str) -> Vec<u8> {
+        let mut b = (s.len() as u16).to_be_bytes().to_vec();
+        b.extend_from_slice(s.as_bytes());
+        b
+    }
+
+    // class Foo implements Serializable { int x; Object o; }
+    fn foo_desc() -> Vec<u8> {
+        let mut b = vec![TC_CLASSDESC];
+        b.extend(utf("Foo"));
+        b.extend([0; 8]);
+        b.extend([SC_SERIALIZABLE, 0x00, 0x02]);
+        b.push(b'I');
+        b.extend(utf("x"));
+        b.push(b'L');
+        b.extend(utf("o"));
+        b.push(TC_STRING);
+        b.extend(utf("Ljava/lang/Object;"));
+        b.extend([TC_ENDBLOCKDATA, TC_NULL]);
+        b
+    }
+
+    #[test]
+    fn strings() {
+        let b = stream(&[&[TC_STRING], &utf("hi")]);
+        assert!(Java.detect(&b));
+        assert_eq!(Java.decode(&b).unwrap(), json!("hi"));
+        let b = stream(&[&[TC_STRING], &utf("hi"), &[TC_BLOCKDATA, 2, 1, 2]]);
+        assert_eq!(
+            Java.decode(&b).unwrap(),
+            json!(["hi", { "type": "binary", "encoding": "base64", "value": "AQI=" }])
+        );
+    }
+
+    #[test]
+    fn objects() {
+        // the handles are the class, its field type and the object
+        let b = stream(&[
+            &[TC_OBJECT],
+            &foo_desc(),
+            &[0, 0, 0, 7],
+            &[TC_REFERENCE, 0x00, 0x7e, 0x00, 0x02],
+        ]);
+        assert_eq!(
+            Java.decode(&b).unwrap(),
+            json!({ "$class": "Foo", "x": 7, "o": { "$ref": "0x7e0002" } })
+        );
+        let b = stream(&[
+            &[TC_ARRAY, TC_CLASSDESC],
+            &utf("[I"),
+            &[0; 8],
+            &[SC_SERIALIZABLE, 0, 0, TC_ENDBLOCKDATA, TC_NULL],
+            &[0, 0, 0, 2, 0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff],
+        ]);
+        assert_eq!(Java.decode(&b).unwrap(), json!([1, -1]));
+    }
+
+    #[test]
+    fn references() {
+        // the referenced value is not copied
+        let b = stream(&[
+            &[TC_STRING],
+            &utf("hi"),
+            &[TC_REFERENCE, 0x00, 0x7e, 0x00, 0x00],
+        ]);
+        assert_eq!(
+            Java.decode(&b).unwrap(),
+            json!(["hi", { "$ref": "0x7e0000" }])
+        );
+        // the handles are cleared by the reset
+        let b = stream(&[
+            &[TC_STRING],
+            &utf("hi"),
+            &[TC_RESET, TC_REFERENCE, 0x00, 0x7e, 0x00, 0x00],
+        ]);
+        assert!(Java.decode(&b).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Java.decode(&[0xac, 0xed, 0x00, 0x06, TC_NULL]).is_err());
+        assert!(Java
+            .decode(&stream(&[&[TC_STRING, 0x00, 0x05, b'h']]))
+            .is_err());
+        assert!(Java.decode(&stream(&[&[0x01]])).is_err());
+        assert!(Java
+            .decode(&stream(&[&[TC_REFERENCE, 0x00, 0x7e, 0x00, 0x09]]))
+            .is_err());
+        assert!(Java
+            .decode(&stream(&[&[TC_BLOCKDATALONG, 0xff, 0xff, 0xff, 0xff]]))
+            .is_err());
+        assert!(Java.decode(&stream(&[&[TC_EXCEPTION]])).is_err());
+        // the reset in the annotation of the class descriptor
+        let mut b = stream(&[&[TC_CLASSDESC, 0x00, 0x01, b'A'], &[0; 8]]);
+        b.extend([
+            SC_SERIALIZABLE,
+            0x00,
+            0x00,
+            TC_RESET,
+            TC_ENDBLOCKDATA,
+            TC_NULL,
+        ]);
+        assert!(Java.decode(&b).is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        // every object holds the next one in its field
+        let mut b = stream(&[&[TC_OBJECT], &foo_desc(), &[0, 0, 0, 0]]);
+        for _ in 0..MAX_DEPTH {
+            b.extend([TC_OBJECT, TC_REFERENCE, 0x00, 0x7e, 0x00, 0x00, 0, 0, 0, 0]);
+        }
+        b.push(TC_NULL);
+        assert!(Java.decode(&b).is_err());
+    }
+}
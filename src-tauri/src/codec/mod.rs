@@ -0,0 +1,357 @@
+use crate::err::CusError;
+use base64::Engine;
+use prost_reflect::MessageDescriptor;
+use serde::Serialize;
+use serde_json::Value;
+
+mod compress;
+mod java;
+mod msgpack;
+mod php;
+mod pickle;
+pub mod protobuf;
+mod text;
+
+// the compression layer, the value is decompressed before it is deserialized
+pub trait Compression: Sync {
+    fn name(&self) -> &'static str;
+    // the bytes look compressed by it
+    fn detect(&self, bytes: &[u8]) -> bool;
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError>;
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CusError>;
+}
+
+// the serialization layer, the bytes are converted to json to be shown and edited
+pub trait Serialization: Sync {
+    fn name(&self) -> &'static str;
+    fn detect(&self, bytes: &[u8]) -> bool;
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError>;
+    // none if the value can only be decoded
+    fn encode(&self, _value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        None
+    }
+    // the value is shown as it is, not as json
+    fn plain(&self) -> bool {
+        false
+    }
+}
+
+// the order is the order of the detection
+const COMPRESSIONS: [&dyn Compression; 5] = [
+    &compress::Gzip,
+    &compress::Zstd,
+    &compress::Lz4,
+    &compress::SnappyFrame,
+    &compress::Snappy,
+];
+
+const SERIALIZATIONS: [&dyn Serialization; 8] = [
+    &text::Json,
+    &php::Php,
+    &java::Java,
+    &pickle::Pickle,
+    &msgpack::MsgPack,
+    &text::Text,
+    &protobuf::Protobuf,
+    &text::Hex,
+];
+
+// the nested compressions are unlikely, it stops the loop of the wrong detection
+const MAX_DEPTH: usize = 4;
+
+#[derive(Serialize)]
+pub struct Info {
+    pub name: &'static str,
+    pub kind: &'static str,
+    pub encodable: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Decoded {
+    // the compressions from the outside, then the serialization
+    pub chain: Vec<String>,
+    // the pretty json, or the plain text
+    pub value: String,
+}
+
+// the bytes in the json, the same shape as the binary payload of the routes
+fn binary(bytes: &[u8]) -> Value {
+    serde_json::json!({
+        "type": "binary",
+        "encoding": "base64",
+        "value": base64::engine::general_purpose::STANDARD.encode(bytes),
+    })
+}
+
+// the bytes of the json made by binary
+fn as_binary(value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+    let obj = value.as_object()?;
+    if obj.len() != 3 || obj.get("type")? != "binary" || obj.get("encoding")? != "base64" {
+        return None;
+    }
+    let v = obj.get("value")?.as_str()?;
+    Some(
+        base64::engine::general_purpose::STANDARD
+            .decode(v)
+            .map_err(|e| CusError::App(e.to_string())),
+    )
+}
+
+fn compression(name: &str) -> Option<&'static dyn Compression> {
+    COMPRESSIONS.into_iter().find(|c| c.name() == name)
+}
+
+fn serialization(name: &str) -> Option<&'static dyn Serialization> {
+    SERIALIZATIONS.into_iter().find(|s| s.name() == name)
+}
+
+fn unknown(name: &str) -> CusError {
+    CusError::App(format!("Unknown codec {}", name))
+}
+
+// the codecs can be chosen by the user
+pub fn list() -> Vec<Info> {
+    let mut codecs: Vec<Info> = COMPRESSIONS
+        .iter()
+        .map(|c| Info {
+            name: c.name(),
+            kind: "compression",
+            encodable: true,
+        })
+        .collect();
+    for s in SERIALIZATIONS {
+        codecs.push(Info {
+            name: s.name(),
+            kind: "serialization",
+            encodable: s.encode(&Value::Null).is_some(),
+        })
+    }
+    codecs
+}
+
+// decode the bytes with the chain, the chain is detected if it is none
+// the protobuf message is decoded by the schema if it is given
+pub fn decode(
+    bytes: &[u8],
+    chain: Option<&[String]>,
+    schema: Option<&MessageDescriptor>,
+) -> Result<Decoded, CusError> {
+    match chain {
+        Some(chain) if !chain.is_empty() => decode_with(bytes, chain, schema),
+        _ => Ok(detect(bytes, schema)),
+    }
+}
+
+fn decode_with(
+    bytes: &[u8],
+    chain: &[String],
+    schema: Option<&MessageDescriptor>,
+) -> Result<Decoded, CusError> {
+    let (last, compressions) = match chain.split_last() {
+        Some(v) => v,
+        None => return Ok(detect(bytes, schema)),
+    };
+    let mut bytes = bytes.to_vec();
+    for name in compressions {
+        let c = compression(name).ok_or_else(|| unknown(name))?;
+        bytes = c.decode(&bytes)?;
+    }
+    // the chain may only have the compressions
+    if let Some(c) = compression(last) {
+        bytes = c.decode(&bytes)?;
+        let mut decoded = detect_serialization(&bytes, schema);
+        let mut full = chain.to_vec();
+        full.append(&mut decoded.chain);
+        decoded.chain = full;
+        return Ok(decoded);
+    }
+    let s = serialization(last).ok_or_else(|| unknown(last))?;
+    Ok(Decoded {
+        chain: chain.to_vec(),
+        value: decode_one(s, &bytes, schema)?,
+    })
+}
+
+// the compressions are peeled while they are detected, then the first serialization matched
+fn detect(bytes: &[u8], schema: Option<&MessageDescriptor>) -> Decoded {
+    let mut chain = vec![];
+    let mut bytes = bytes.to_vec();
+    while chain.len() < MAX_DEPTH {
+        let decompressed = COMPRESSIONS
+            .iter()
+            .filter(|c| c.detect(&bytes))
+            .find_map(|c| c.decode(&bytes).ok().map(|b| (c.name(), b)));
+        match decompressed {
+            Some((name, b)) => {
+                chain.push(name.to_string());
+                bytes = b;
+            }
+            None => break,
+        }
+    }
+    let mut decoded = detect_serialization(&bytes, schema);
+    chain.append(&mut decoded.chain);
+    decoded.chain = chain;
+    decoded
+}
+
+// the message of the schema goes first, its bytes may also be taken as the text
+fn detect_serialization(bytes: &[u8], schema: Option<&MessageDescriptor>) -> Decoded {
+    let first: Option<&dyn Serialization> =
+        schema.map(|_| &protobuf::Protobuf as &dyn Serialization);
+    for s in first.into_iter().chain(SERIALIZATIONS) {
+        if !s.detect(bytes) {
+            continue;
+        }
+        if let Ok(value) = decode_one(s, bytes, schema) {
+            return Decoded {
+                chain: vec![s.name().to_string()],
+                value,
+            };
+        }
+    }
+    // hex accepts any bytes
+    Decoded {
+        chain: vec![text::Hex.name().to_string()],
+        value: hex::encode(bytes),
+    }
+}
+
+fn decode_one(
+    s: &dyn Serialization,
+    bytes: &[u8],
+    schema: Option<&MessageDescriptor>,
+) -> Result<String, CusError> {
+    match schema {
+        Some(desc) if s.name() == protobuf::Protobuf.name() => {
+            protobuf::decode_with(desc.clone(), bytes)
+        }
+        _ => format(s, &s.decode(bytes)?),
+    }
+}
+
+fn format(s: &dyn Serialization, value: &Value) -> Result<String, CusError> {
+    match value {
+        Value::String(v) if s.plain() => Ok(v.clone()),
+        _ => Ok(serde_json::to_string_pretty(value)?),
+    }
+}
+
+// encode the edited value with the chain in reverse order
+pub fn encode(
+    value: &str,
+    chain: &[String],
+    schema: Option<&MessageDescriptor>,
+) -> Result<Vec<u8>, CusError> {
+    let (last, compressions) = chain
+        .split_last()
+        .ok_or_else(|| CusError::build("The codec chain is empty"))?;
+    let s = serialization(last).ok_or_else(|| unknown(last))?;
+    let mut bytes = match schema {
+        Some(desc) if s.name() == protobuf::Protobuf.name() => {
+            protobuf::encode_with(desc.clone(), value)?
+        }
+        _ => encode_one(s, value)?,
+    };
+    for name in compressions.iter().rev() {
+        let c = compression(name).ok_or_else(|| unknown(name))?;
+        bytes = c.encode(&bytes)?;
+    }
+    Ok(bytes)
+}
+
+fn encode_one(s: &dyn Serialization, value: &str) -> Result<Vec<u8>, CusError> {
+    let value = match s.plain() {
+        true => Value::String(value.to_string()),
+        false => serde_json::from_str(value)?,
+    };
+    match s.encode(&value) {
+        Some(r) => r,
+        None => Err(CusError::App(format!(
+            "The value cannot be encoded as {}",
+            s.name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn detect_chain() {
+        let json = r#"{"a":[1,"x"]}"#;
+        let pretty = serde_json::to_string_pretty(&serde_json::json!({ "a": [1, "x"] })).unwrap();
+        for names in [
+            &["json"][..],
+            &["gzip", "json"],
+            &["zstd", "gzip", "json"],
+            &["lz4", "msgpack"],
+        ] {
+            let bytes = encode(json, &chain(names), None).unwrap();
+            let decoded = decode(&bytes, None, None).unwrap();
+            assert_eq!(decoded.chain, chain(names));
+            assert_eq!(decoded.value, pretty);
+        }
+        let decoded = decode(b"hello", None, None).unwrap();
+        assert_eq!(decoded.chain, chain(&["text"]));
+        assert_eq!(decoded.value, "hello");
+        // any bytes can be shown as hex
+        let decoded = decode(&[0xff, 0xfe], None, None).unwrap();
+        assert_eq!(decoded.chain, chain(&["hex"]));
+        assert_eq!(decoded.value, "fffe");
+    }
+
+    #[test]
+    fn given_chain() {
+        let bytes = encode("[1]", &chain(&["gzip", "json"]), None).unwrap();
+        // the chain of the compressions only is completed by the detection
+        let decoded = decode(&bytes, Some(&chain(&["gzip"])), None).unwrap();
+        assert_eq!(decoded.chain, chain(&["gzip", "json"]));
+        // the bytes are shown as the given serialization
+        let decoded = decode(&bytes, Some(&chain(&["gzip", "text"])), None).unwrap();
+        assert_eq!(decoded.value, "[1]");
+        let bytes = encode("00ff", &chain(&["hex"]), None).unwrap();
+        assert_eq!(bytes, [0x00, 0xff]);
+    }
+
+    #[test]
+    fn invalid_chain() {
+        assert!(decode(b"x", Some(&chain(&["nope"])), None).is_err());
+        assert!(decode(b"x", Some(&chain(&["gzip", "json"])), None).is_err());
+        assert!(decode(b"x", Some(&chain(&["json"])), None).is_err());
+        assert!(encode("1", &[], None).is_err());
+        assert!(encode("1", &chain(&["nope"]), None).is_err());
+        assert!(encode("{", &chain(&["json"]), None).is_err());
+        // java is only decoded
+        assert!(encode("{}", &chain(&["java"]), None).is_err());
+    }
+
+    #[test]
+    fn protobuf_schema() {
+        let files = [(
+            "user.proto".to_string(),
+            "syntax = \"proto3\"; message User { string name = 1; }".to_string(),
+        )];
+        let set = protobuf::compile(&files).unwrap();
+        let desc = protobuf::descriptor(&set, "User").unwrap();
+        let bytes = [0x0a, 0x02, b'h', b'i'];
+        // the bytes are text too, the message of the schema goes first
+        assert_eq!(decode(&bytes, None, None).unwrap().chain, chain(&["text"]));
+        let pretty = serde_json::to_string_pretty(&serde_json::json!({ "name": "hi" })).unwrap();
+        let decoded = decode(&bytes, None, Some(&desc)).unwrap();
+        assert_eq!(decoded.chain, chain(&["protobuf"]));
+        assert_eq!(decoded.value, pretty);
+        let compressed = encode(&pretty, &chain(&["gzip", "protobuf"]), Some(&desc)).unwrap();
+        let decoded = decode(&compressed, Some(&chain(&["gzip"])), Some(&desc)).unwrap();
+        assert_eq!(decoded.chain, chain(&["gzip", "protobuf"]));
+        assert_eq!(decoded.value, pretty);
+        // the schema is only used by protobuf
+        let decoded = decode(&bytes, Some(&chain(&["hex"])), Some(&desc)).unwrap();
+        assert_eq!(decoded.value, "0a026869");
+    }
+}
@@ -0,0 +1,149 @@
+use super::{as_binary, binary, Serialization};
+use crate::err::CusError;
+use rmpv::Value as MsgValue;
+use serde_json::{Map, Number, Value};
+
+pub struct MsgPack;
+
+impl Serialization for MsgPack {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+    // only the map and the array are detected, a scalar is too likely to be something else
+    fn detect(&self, bytes: &[u8]) -> bool {
+        matches!(bytes.first(), Some(0x80..=0x9f | 0xdc..=0xdf)) && read(bytes).is_ok()
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        Ok(to_json(read(bytes)?))
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(from_json(value).and_then(|v| {
+            let mut out = vec![];
+            rmpv::encode::write_value(&mut out, &v).map_err(|e| CusError::App(e.to_string()))?;
+            Ok(out)
+        }))
+    }
+}
+
+// the value must take all the bytes
+fn read(bytes: &[u8]) -> Result<MsgValue, CusError> {
+    let mut rd = bytes;
+    let v = rmpv::decode::read_value(&mut rd).map_err(|e| CusError::App(e.to_string()))?;
+    if !rd.is_empty() {
+        return Err(CusError::build("Trailing bytes after the msgpack value"));
+    }
+    Ok(v)
+}
+
+fn to_json(v: MsgValue) -> Value {
+    match v {
+        MsgValue::Nil => Value::Null,
+        MsgValue::Boolean(b) => Value::Bool(b),
+        MsgValue::Integer(i) => match i.as_i64() {
+            Some(i) => Value::from(i),
+            None => Value::from(i.as_u64().unwrap_or_default()),
+        },
+        MsgValue::F32(f) => Number::from_f64(f as f64).map_or(Value::Null, Value::Number),
+        MsgValue::F64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        MsgValue::String(s) => match s.into_str() {
+            Some(s) => Value::String(s),
+            None => Value::Null,
+        },
+        MsgValue::Binary(b) => binary(&b),
+        MsgValue::Array(items) => Value::Array(items.into_iter().map(to_json).collect()),
+        MsgValue::Map(items) => {
+            let mut map = Map::new();
+            for (k, v) in items {
+                let k = match k {
+                    MsgValue::String(s) => s.into_str().unwrap_or_default(),
+                    k => to_json(k).to_string(),
+                };
+                map.insert(k, to_json(v));
+            }
+            Value::Object(map)
+        }
+        MsgValue::Ext(kind, data) => serde_json::json!({
+            "type": "ext",
+            "kind": kind,
+            "value": binary(&data),
+        }),
+    }
+}
+
+fn from_json(v: &Value) -> Result<MsgValue, CusError> {
+    if let Some(b) = as_binary(v) {
+        return Ok(MsgValue::Binary(b?));
+    }
+    Ok(match v {
+        Value::Null => MsgValue::Nil,
+        Value::Bool(b) => MsgValue::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => MsgValue::from(i),
+            (_, Some(u)) => MsgValue::from(u),
+            _ => MsgValue::F64(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => MsgValue::from(s.as_str()),
+        Value::Array(items) => {
+            MsgValue::Array(items.iter().map(from_json).collect::<Result<_, _>>()?)
+        }
+        Value::Object(map) => {
+            if map.get("type").is_some_and(|t| t == "ext") {
+                if let (Some(kind), Some(Ok(data))) = (
+                    map.get("kind").and_then(|k| k.as_i64()),
+                    map.get("value").and_then(as_binary),
+                ) {
+                    return Ok(MsgValue::Ext(kind as i8, data));
+                }
+            }
+            let mut items = vec![];
+            for (k, v) in map {
+                items.push((MsgValue::from(k.as_str()), from_json(v)?));
+            }
+            MsgValue::Map(items)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(v: Value) -> Vec<u8> {
+        let b = MsgPack.encode(&v).unwrap().unwrap();
+        assert_eq!(MsgPack.decode(&b).unwrap(), v);
+        b
+    }
+
+    #[test]
+    fn maps() {
+        let b = [0x81, 0xa1, b'a', 0x01];
+        assert!(MsgPack.detect(&b));
+        assert_eq!(MsgPack.decode(&b).unwrap(), json!({ "a": 1 }));
+        assert_eq!(round_trip(json!({ "a": 1 })), b);
+        round_trip(json!({ "z": [1, -2, 1.5, "x", null, true], "a": { "b": [] } }));
+        round_trip(json!([u64::MAX, i64::MIN]));
+    }
+
+    #[test]
+    fn binary_and_ext() {
+        let bin = json!({ "type": "binary", "encoding": "base64", "value": "AQI=" });
+        assert_eq!(round_trip(json!([bin])), [0x91, 0xc4, 0x02, 0x01, 0x02]);
+        round_trip(json!({ "e": { "type": "ext", "kind": 5, "value": bin } }));
+        // the keys which are not strings are shown as json
+        assert_eq!(
+            MsgPack.decode(&[0x81, 0x01, 0xc3]).unwrap(),
+            json!({ "1": true })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        // the scalar is not detected
+        assert!(!MsgPack.detect(&[0x01]));
+        assert!(!MsgPack.detect(b"{\"a\":1}"));
+        assert!(MsgPack.decode(&[0x81, 0xa1, b'a', 0x01, 0x00]).is_err());
+        assert!(!MsgPack.detect(&[0x82, 0xa1, b'a', 0x01]));
+        assert!(MsgPack.decode(&[0x82, 0xa1, b'a', 0x01]).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+use super::Serialization;
+use crate::err::CusError;
+use base64::Engine;
+use serde_json::{Map, Number, Value};
+
+// the php serialize format
+// the object is {"__class": name, ...properties}, the private and protected property names are kept as php writes them
+// the reference is {"__r": n} or {"__R": n} by the php slot number, so the value is written back as it was read
+// the markers start with __, a php key starting with __ gets one more _ so it is never taken as a marker
+pub struct Php;
+
+impl Serialization for Php {
+    fn name(&self) -> &'static str {
+        "php"
+    }
+    // only the array and the object are detected, a scalar is too likely to be a plain text
+    fn detect(&self, bytes: &[u8]) -> bool {
+        matches!(bytes, [b'a' | b'O' | b'C', b':', ..])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        unserialize(bytes)
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(serialize(value))
+    }
+}
+
+const CLASS: &str = "__class";
+const SERIALIZED: &str = "__serialized";
+const ENUM: &str = "__enum";
+const FLOAT: &str = "__float";
+const REF: &str = "__r";
+const VAR_REF: &str = "__R";
+// the string which is not utf-8, in base64
+const BINARY: &str = "__binary";
+
+// the nested arrays and objects, a deeper value is rejected before the stack runs out
+const MAX_DEPTH: usize = 100;
+
+fn escape(key: String) -> String {
+    match key.starts_with("__") {
+        true => format!("_{}", key),
+        false => key,
+    }
+}
+
+fn unescape(key: &str) -> &str {
+    match key.starts_with("___") {
+        true => &key[1..],
+        false => key,
+    }
+}
+
+fn invalid(pos: usize) -> CusError {
+    CusError::App(format!("Invalid php serialization at offset {}", pos))
+}
+
+pub fn unserialize(bytes: &[u8]) -> Result<Value, CusError> {
+    let mut p = Parser {
+        bytes,
+        pos: 0,
+        slots: 0,
+        depth: 0,
+    };
+    let v = p.value(true)?;
+    if p.pos != bytes.len() {
+        return Err(invalid(p.pos));
+    }
+    Ok(v)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    // the count of the values can be referenced
+    slots: u64,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn u8(&mut self) -> Result<u8, CusError> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| invalid(self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), CusError> {
+        match self.u8()? == b {
+            true => Ok(()),
+            false => Err(invalid(self.pos - 1)),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CusError> {
+        let end = self.pos.checked_add(n).ok_or_else(|| invalid(self.pos))?;
+        let b = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| invalid(self.pos))?;
+        self.pos = end;
+        Ok(b)
+    }
+
+    // the text until the delimiter, the delimiter is skipped
+    fn until(&mut self, delimiter: u8) -> Result<&'a str, CusError> {
+        let start = self.pos;
+        let len = self.bytes[start..]
+            .iter()
+            .position(|b| *b == delimiter)
+            .ok_or_else(|| invalid(start))?;
+        self.pos += len + 1;
+        std::str::from_utf8(&self.bytes[start..start + len]).map_err(|_| invalid(start))
+    }
+
+    fn int<T: std::str::FromStr>(&mut self, delimiter: u8) -> Result<T, CusError> {
+        let start = self.pos;
+        self.until(delimiter)?.parse().map_err(|_| invalid(start))
+    }
+
+    // the "name" with the byte length
+    fn quoted(&mut self, len: usize) -> Result<&'a [u8], CusError> {
+        self.expect(b'"')?;
+        let s = self.take(len)?;
+        self.expect(b'"')?;
+        Ok(s)
+    }
+
+    fn value(&mut self, slot: bool) -> Result<Value, CusError> {
+        let start = self.pos;
+        let tag = self.u8()?;
+        // every value has a slot except the array key and the R reference
+        if slot && tag != b'R' {
+            self.slots += 1;
+        }
+        if tag == b'N' {
+            self.expect(b';')?;
+            return Ok(Value::Null);
+        }
+        self.expect(b':')?;
+        match tag {
+            b'b' => Ok(Value::Bool(self.int::<u8>(b';')? != 0)),
+            b'i' => Ok(Value::from(self.int::<i64>(b';')?)),
+            b'd' => {
+                let s = self.until(b';')?;
+                let f: f64 = s.parse().map_err(|_| invalid(start))?;
+                Ok(match Number::from_f64(f) {
+                    Some(n) => Value::Number(n),
+                    None => serde_json::json!({ FLOAT: s }),
+                })
+            }
+            b's' => {
+                let len = self.int(b':')?;
+                let s = self.quoted(len)?;
+                self.expect(b';')?;
+                Ok(string(s))
+            }
+            b'S' => {
+                let len = self.int(b':')?;
+                self.expect(b'"')?;
+                let s = self.escaped(len)?;
+                self.expect(b'"')?;
+                self.expect(b';')?;
+                Ok(string(&s))
+            }
+            b'a' => self.nested(Self::array),
+            b'O' => self.nested(Self::object),
+            // the object of the Serializable interface, the data is written by the class itself
+            b'C' => {
+                let len = self.int(b':')?;
+                let class = String::from_utf8_lossy(self.quoted(len)?).into_owned();
+                self.expect(b':')?;
+                let len = self.int(b':')?;
+                self.expect(b'{')?;
+                let data = string(self.take(len)?);
+                self.expect(b'}')?;
+                Ok(serde_json::json!({ CLASS: class, SERIALIZED: data }))
+            }
+            b'E' => {
+                let len = self.int(b':')?;
+                let name = String::from_utf8_lossy(self.quoted(len)?).into_owned();
+                self.expect(b';')?;
+                Ok(serde_json::json!({ ENUM: name }))
+            }
+            b'r' | b'R' => {
+                let n: u64 = self.int(b';')?;
+                if n == 0 || n > self.slots {
+                    return Err(invalid(start));
+                }
+                let name = if tag == b'r' { REF } else { VAR_REF };
+                Ok(serde_json::json!({ name: n }))
+            }
+            _ => Err(invalid(start)),
+        }
+    }
+
+    // run the nested read, counting the depth
+    fn nested(
+        &mut self,
+        read: fn(&mut Self) -> Result<Value, CusError>,
+    ) -> Result<Value, CusError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid(self.pos));
+        }
+        self.depth += 1;
+        let r = read(self);
+        self.depth -= 1;
+        r
+    }
+
+    fn array(&mut self) -> Result<Value, CusError> {
+        let count: usize = self.int(b':')?;
+        self.expect(b'{')?;
+        let mut items = vec![];
+        for _ in 0..count {
+            let k = self.key()?;
+            items.push((k, self.value(true)?));
+        }
+        self.expect(b'}')?;
+        Ok(array(items))
+    }
+
+    fn object(&mut self) -> Result<Value, CusError> {
+        let len = self.int(b':')?;
+        let class = String::from_utf8_lossy(self.quoted(len)?).into_owned();
+        self.expect(b':')?;
+        let count: usize = self.int(b':')?;
+        self.expect(b'{')?;
+        let mut map = Map::new();
+        map.insert(CLASS.into(), Value::String(class));
+        for _ in 0..count {
+            let k = match self.key()? {
+                Value::String(s) => escape(s),
+                k => k.to_string(),
+            };
+            let v = self.value(true)?;
+            map.insert(k, v);
+        }
+        self.expect(b'}')?;
+        Ok(Value::Object(map))
+    }
+
+    // the array key is an int or a string
+    fn key(&mut self) -> Result<Value, CusError> {
+        let start = self.pos;
+        match self.value(false)? {
+            k @ (Value::Number(_) | Value::String(_)) => Ok(k),
+            _ => Err(invalid(start)),
+        }
+    }
+
+    // the S string, the bytes out of the ascii are escaped as \xx
+    fn escaped(&mut self, len: usize) -> Result<Vec<u8>, CusError> {
+        // the length is not trusted before the bytes are read
+        let mut out = Vec::with_capacity(len.min(self.bytes.len() - self.pos));
+        for _ in 0..len {
+            match self.u8()? {
+                b'\\' => {
+                    let start = self.pos;
+                    let h = self.take(2)?;
+                    let b = std::str::from_utf8(h)
+                        .ok()
+                        .and_then(|h| u8::from_str_radix(h, 16).ok())
+                        .ok_or_else(|| invalid(start))?;
+                    out.push(b)
+                }
+                b => out.push(b),
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn string(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Value::String(s.to_string()),
+        Err(_) => serde_json::json!({
+            BINARY: base64::engine::general_purpose::STANDARD.encode(bytes)
+        }),
+    }
+}
+
+fn as_binary(v: &str) -> Result<Vec<u8>, CusError> {
+    base64::engine::general_purpose::STANDARD
+        .decode(v)
+        .map_err(|e| CusError::App(e.to_string()))
+}
+
+// the list if the keys are 0..n, otherwise the object
+fn array(items: Vec<(Value, Value)>) -> Value {
+    let is_list = items
+        .iter()
+        .enumerate()
+        .all(|(i, (k, _))| k.as_u64() == Some(i as u64));
+    if is_list {
+        return Value::Array(items.into_iter().map(|(_, v)| v).collect());
+    }
+    let mut map = Map::new();
+    for (k, v) in items {
+        let k = match k {
+            Value::String(s) => escape(s),
+            k => k.to_string(),
+        };
+        map.insert(k, v);
+    }
+    Value::Object(map)
+}
+
+pub fn serialize(value: &Value) -> Result<Vec<u8>, CusError> {
+    let mut out = vec![];
+    write(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_str(out: &mut Vec<u8>, s: &[u8]) {
+    out.extend(format!("s:{}:\"", s.len()).as_bytes());
+    out.extend(s);
+    out.extend(b"\";");
+}
+
+// the key is an int if php takes it as an int
+fn write_key(out: &mut Vec<u8>, k: &str) {
+    match k.parse::<i64>() {
+        Ok(i) if i.to_string() == k => out.extend(format!("i:{};", i).as_bytes()),
+        _ => write_str(out, k.as_bytes()),
+    }
+}
+
+fn write(out: &mut Vec<u8>, value: &Value) -> Result<(), CusError> {
+    match value {
+        Value::Null => out.extend(b"N;"),
+        Value::Bool(b) => out.extend(format!("b:{};", *b as u8).as_bytes()),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => out.extend(format!("i:{};", i).as_bytes()),
+            None => out.extend(format!("d:{};", n.as_f64().unwrap_or_default()).as_bytes()),
+        },
+        Value::String(s) => write_str(out, s.as_bytes()),
+        Value::Array(items) => {
+            out.extend(format!("a:{}:{{", items.len()).as_bytes());
+            for (i, v) in items.iter().enumerate() {
+                out.extend(format!("i:{};", i).as_bytes());
+                write(out, v)?;
+            }
+            out.push(b'}');
+        }
+        Value::Object(map) => write_object(out, map)?,
+    }
+    Ok(())
+}
+
+fn write_object(out: &mut Vec<u8>, map: &Map<String, Value>) -> Result<(), CusError> {
+    let one = |name: &str| match map.len() {
+        1 => map.get(name),
+        _ => None,
+    };
+    if let Some(r) = one(REF).or_else(|| one(VAR_REF)) {
+        let tag = if map.contains_key(REF) { 'r' } else { 'R' };
+        let n = r
+            .as_u64()
+            .ok_or_else(|| CusError::build("Invalid php reference"))?;
+        out.extend(format!("{}:{};", tag, n).as_bytes());
+        return Ok(());
+    }
+    if let Some(name) = one(ENUM).and_then(|v| v.as_str()) {
+        out.extend(format!("E:{}:\"{}\";", name.len(), name).as_bytes());
+        return Ok(());
+    }
+    if let Some(f) = one(FLOAT).and_then(|v| v.as_str()) {
+        out.extend(format!("d:{};", f).as_bytes());
+        return Ok(());
+    }
+    if let Some(b) = one(BINARY).and_then(|v| v.as_str()) {
+        write_str(out, &as_binary(b)?);
+        return Ok(());
+    }
+    let class = match map.get(CLASS) {
+        Some(Value::String(class)) => class,
+        _ => {
+            out.extend(format!("a:{}:{{", map.len()).as_bytes());
+            for (k, v) in map {
+                write_key(out, unescape(k));
+                write(out, v)?;
+            }
+            out.push(b'}');
+            return Ok(());
+        }
+    };
+    if let (2, Some(data)) = (map.len(), map.get(SERIALIZED)) {
+        let data = match data.get(BINARY).and_then(|v| v.as_str()) {
+            Some(b) => as_binary(b)?,
+            None => data.as_str().unwrap_or_default().as_bytes().to_vec(),
+        };
+        out.extend(format!("C:{}:\"{}\":{}:{{", class.len(), class, data.len()).as_bytes());
+        out.extend(data);
+        out.push(b'}');
+        return Ok(());
+    }
+    out.extend(format!("O:{}:\"{}\":{}:{{", class.len(), class, map.len() - 1).as_bytes());
+    for (k, v) in map.iter().filter(|(k, _)| *k != CLASS) {
+        write_str(out, unescape(k).as_bytes());
+        write(out, v)?;
+    }
+    out.push(b'}');
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(s: &[u8]) -> Value {
+        let v = unserialize(s).unwrap();
+        assert_eq!(serialize(&v).unwrap(), s);
+        v
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(round_trip(b"N;"), Value::Null);
+        assert_eq!(round_trip(b"b:1;"), json!(true));
+        assert_eq!(round_trip(b"i:-42;"), json!(-42));
+        assert_eq!(round_trip(b"d:0.5;"), json!(0.5));
+        assert_eq!(round_trip(b"s:5:\"hello\";"), json!("hello"));
+        // the length is in bytes
+        assert_eq!(round_trip("s:6:\"你好\";".as_bytes()), json!("你好"));
+        assert_eq!(round_trip(b"d:INF;"), json!({ "__float": "INF" }));
+    }
+
+    #[test]
+    fn nested_arrays() {
+        let s = b"a:2:{i:0;s:1:\"a\";i:1;a:2:{s:3:\"key\";i:1;i:5;a:0:{}}}";
+        assert_eq!(round_trip(s), json!(["a", { "key": 1, "5": [] }]));
+        // the order of the keys is kept
+        let s = b"a:2:{s:1:\"z\";i:1;s:1:\"a\";i:2;}";
+        assert_eq!(
+            serde_json::to_string(&round_trip(s)).unwrap(),
+            r#"{"z":1,"a":2}"#
+        );
+    }
+
+    #[test]
+    fn objects() {
+        let s =
+            b"O:3:\"Foo\":3:{s:4:\"name\";s:3:\"bar\";s:6:\"\0*\0age\";i:3;s:8:\"\0Foo\0tag\";N;}";
+        assert_eq!(
+            round_trip(s),
+            json!({ "__class": "Foo", "name": "bar", "\0*\0age": 3, "\0Foo\0tag": null })
+        );
+        let s = b"C:11:\"ArrayObject\":21:{x:i:0;a:0:{};m:a:0:{}}";
+        assert_eq!(
+            round_trip(s),
+            json!({ "__class": "ArrayObject", "__serialized": "x:i:0;a:0:{};m:a:0:{}" })
+        );
+        assert_eq!(
+            round_trip(b"E:10:\"Suit:Heart\";"),
+            json!({ "__enum": "Suit:Heart" })
+        );
+    }
+
+    #[test]
+    fn references() {
+        // $o = new stdClass; [$o, $o]
+        let s = b"a:2:{i:0;O:8:\"stdClass\":0:{}i:1;r:2;}";
+        assert_eq!(
+            round_trip(s),
+            json!([{ "__class": "stdClass" }, { "__r": 2 }])
+        );
+        // $a = [1]; $a[] = &$a[0];
+        let s = b"a:2:{i:0;i:1;i:1;R:2;}";
+        assert_eq!(round_trip(s), json!([1, { "__R": 2 }]));
+        // the slot must exist
+        assert!(unserialize(b"a:1:{i:0;r:3;}").is_err());
+    }
+
+    #[test]
+    fn binary_strings() {
+        let s = b"s:3:\"\xff\x00\x01\";";
+        assert_eq!(round_trip(s), json!({ "__binary": "/wAB" }));
+        assert_eq!(
+            unserialize(b"S:3:\"\\ffab\";").unwrap(),
+            json!({ "__binary": "/2Fi" })
+        );
+        let s = b"C:3:\"Foo\":2:{\xff\x00}";
+        assert_eq!(
+            round_trip(s),
+            json!({ "__class": "Foo", "__serialized": { "__binary": "/wA=" } })
+        );
+    }
+
+    #[test]
+    fn marker_keys() {
+        // the arrays look like the markers
+        for s in [
+            &b"a:1:{s:3:\"__r\";i:2;}"[..],
+            b"a:1:{s:3:\"__R\";i:2;}",
+            b"a:1:{s:6:\"__enum\";s:1:\"x\";}",
+            b"a:1:{s:7:\"__float\";s:3:\"INF\";}",
+            b"a:1:{s:8:\"__binary\";s:4:\"/wA=\";}",
+            b"a:2:{s:7:\"__class\";s:3:\"Foo\";s:1:\"a\";i:1;}",
+            b"a:1:{s:4:\"___r\";i:2;}",
+            b"O:3:\"Foo\":1:{s:7:\"__class\";s:3:\"Bar\";}",
+        ] {
+            round_trip(s);
+        }
+        assert_eq!(
+            unserialize(b"a:1:{s:3:\"__r\";i:2;}").unwrap(),
+            json!({ "___r": 2 })
+        );
+        // the binary shape of the other codecs is a plain array
+        let s = b"a:3:{s:4:\"type\";s:6:\"binary\";s:8:\"encoding\";s:6:\"base64\";s:5:\"value\";s:4:\"AQI=\";}";
+        assert_eq!(
+            round_trip(s),
+            json!({ "type": "binary", "encoding": "base64", "value": "AQI=" })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(unserialize(b"s:5:\"abc\";").is_err());
+        assert!(unserialize(b"a:1:{i:0;").is_err());
+        assert!(unserialize(b"i:1;trailing").is_err());
+        assert!(unserialize(b"x:1;").is_err());
+        assert!(unserialize(b"S:1000000000000:\"\";").is_err());
+        assert!(unserialize(b"S:18446744073709551615:\"\";").is_err());
+        // every array holds the next one
+        let nested = |n: usize| {
+            let mut s = "a:1:{i:0;".repeat(n);
+            s.push_str("N;");
+            s.push_str(&"}".repeat(n));
+            s
+        };
+        assert!(unserialize(nested(MAX_DEPTH).as_bytes()).is_ok());
+        assert!(unserialize(nested(MAX_DEPTH + 1).as_bytes()).is_err());
+        assert!(unserialize(nested(100000).as_bytes()).is_err());
+        assert!(unserialize(b"O:3:\"Foo\":1:{s:1:\"a\";".repeat(100000).as_slice()).is_err());
+    }
+}
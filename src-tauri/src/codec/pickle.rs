@@ -0,0 +1,121 @@
+use super::{as_binary, binary, Serialization};
+use crate::err::CusError;
+use serde_json::{Map, Number, Value};
+use serde_pickle::{DeOptions, HashableValue, SerOptions, Value as PickleValue};
+use std::collections::BTreeMap;
+
+pub struct Pickle;
+
+impl Serialization for Pickle {
+    fn name(&self) -> &'static str {
+        "pickle"
+    }
+    // the protocol 2 and above starts with PROTO and ends with STOP
+    fn detect(&self, bytes: &[u8]) -> bool {
+        matches!(bytes, [0x80, 2..=5, .., b'.'])
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        // the classes can not be resolved, they are replaced with none
+        let options = DeOptions::new()
+            .decode_strings()
+            .replace_unresolved_globals();
+        let v = serde_pickle::value_from_slice(bytes, options)
+            .map_err(|e| CusError::App(e.to_string()))?;
+        Ok(to_json(v))
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(from_json(value).and_then(|v| {
+            serde_pickle::value_to_vec(&v, SerOptions::new())
+                .map_err(|e| CusError::App(e.to_string()))
+        }))
+    }
+}
+
+fn to_json(v: PickleValue) -> Value {
+    match v {
+        PickleValue::None => Value::Null,
+        PickleValue::Bool(b) => Value::Bool(b),
+        PickleValue::I64(i) => Value::from(i),
+        // the big int is kept as its digits
+        PickleValue::Int(i) => Value::String(i.to_string()),
+        PickleValue::F64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        PickleValue::Bytes(b) => binary(&b),
+        PickleValue::String(s) => Value::String(s),
+        PickleValue::List(items) | PickleValue::Tuple(items) => {
+            Value::Array(items.into_iter().map(to_json).collect())
+        }
+        PickleValue::Set(items) | PickleValue::FrozenSet(items) => {
+            Value::Array(items.into_iter().map(|v| to_json(v.into_value())).collect())
+        }
+        PickleValue::Dict(items) => {
+            let mut map = Map::new();
+            for (k, v) in items {
+                let k = match k {
+                    HashableValue::String(s) => s,
+                    k => to_json(k.into_value()).to_string(),
+                };
+                map.insert(k, to_json(v));
+            }
+            Value::Object(map)
+        }
+    }
+}
+
+fn from_json(v: &Value) -> Result<PickleValue, CusError> {
+    if let Some(b) = as_binary(v) {
+        return Ok(PickleValue::Bytes(b?));
+    }
+    Ok(match v {
+        Value::Null => PickleValue::None,
+        Value::Bool(b) => PickleValue::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => PickleValue::I64(i),
+            None => PickleValue::F64(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => PickleValue::String(s.clone()),
+        Value::Array(items) => {
+            PickleValue::List(items.iter().map(from_json).collect::<Result<_, _>>()?)
+        }
+        Value::Object(map) => {
+            let mut items = BTreeMap::new();
+            for (k, v) in map {
+                items.insert(HashableValue::String(k.clone()), from_json(v)?);
+            }
+            PickleValue::Dict(items)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(v: Value) {
+        let b = Pickle.encode(&v).unwrap().unwrap();
+        assert!(Pickle.detect(&b));
+        assert_eq!(Pickle.decode(&b).unwrap(), v);
+    }
+
+    #[test]
+    fn values() {
+        // pickle.dumps({'a': (1, 2)}, protocol=4)
+        let b = [
+            0x80, 0x04, 0x95, 0x0e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7d, 0x94, 0x8c,
+            0x01, b'a', 0x94, 0x4b, 0x01, 0x4b, 0x02, 0x86, 0x94, 0x73, 0x2e,
+        ];
+        assert!(Pickle.detect(&b));
+        assert_eq!(Pickle.decode(&b).unwrap(), json!({ "a": [1, 2] }));
+        // the dict is written in the order of the keys
+        round_trip(json!({ "a": [1, -2, 2.5, "x", null, true], "b": {} }));
+        round_trip(json!({ "type": "binary", "encoding": "base64", "value": "AQI=" }));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(!Pickle.detect(b"hello"));
+        assert!(!Pickle.detect(&[0x80, 0x01, b'.']));
+        assert!(Pickle.decode(&[0x80, 0x02, 0xff, b'.']).is_err());
+        assert!(Pickle.decode(&[0x80, 0x02, 0x4b]).is_err());
+    }
+}
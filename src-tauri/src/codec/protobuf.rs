@@ -0,0 +1,448 @@
+use super::{as_binary, binary, Serialization};
+use crate::err::CusError;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use protox::file::{ChainFileResolver, File, FileResolver, GoogleFileResolver};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+// the protobuf without the schema, the fields are dumped by their numbers
+pub struct Protobuf;
+
+impl Serialization for Protobuf {
+    fn name(&self) -> &'static str {
+        "protobuf"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        !bytes.is_empty() && message(bytes, 0).is_some()
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        message(bytes, 0).ok_or_else(|| CusError::build("Invalid protobuf message"))
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(match value {
+            Value::Object(map) => encode_message(map),
+            _ => Err(CusError::build("The protobuf message must be an object")),
+        })
+    }
+}
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LEN: u8 = 2;
+const FIXED32: u8 = 5;
+
+// the nested messages, a deeper one is shown as the bytes before the stack runs out
+const MAX_DEPTH: usize = 100;
+
+fn varint(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut v: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let b = *bytes.get(*pos)?;
+        *pos += 1;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some(v);
+        }
+    }
+    None
+}
+
+// the message must take all the bytes, or it is not a message
+fn message(bytes: &[u8], depth: usize) -> Option<Value> {
+    let mut map = Map::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let tag = varint(bytes, &mut pos)?;
+        let number = tag >> 3;
+        if number == 0 {
+            return None;
+        }
+        let value = match (tag & 0x07) as u8 {
+            VARINT => Value::from(varint(bytes, &mut pos)?),
+            FIXED64 => {
+                let b = bytes.get(pos..pos + 8)?;
+                pos += 8;
+                serde_json::json!({
+                    "type": "fixed64",
+                    "value": u64::from_le_bytes(b.try_into().ok()?),
+                })
+            }
+            FIXED32 => {
+                let b = bytes.get(pos..pos + 4)?;
+                pos += 4;
+                serde_json::json!({
+                    "type": "fixed32",
+                    "value": u32::from_le_bytes(b.try_into().ok()?),
+                })
+            }
+            LEN => {
+                let len = varint(bytes, &mut pos)? as usize;
+                let b = bytes.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+                length_delimited(b, depth)
+            }
+            _ => return None,
+        };
+        // the repeated field is an array
+        match map.get_mut(&number.to_string()) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(v) => *v = Value::Array(vec![v.take(), value]),
+            None => {
+                map.insert(number.to_string(), value);
+            }
+        }
+    }
+    Some(Value::Object(map))
+}
+
+// the string, the nested message or the bytes
+fn length_delimited(b: &[u8], depth: usize) -> Value {
+    if let Ok(s) = std::str::from_utf8(b) {
+        if !s.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+            return Value::String(s.to_string());
+        }
+    }
+    if depth >= MAX_DEPTH {
+        return binary(b);
+    }
+    match message(b, depth + 1) {
+        Some(v) if !b.is_empty() => v,
+        _ => binary(b),
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8 & 0x7f) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn write_tag(out: &mut Vec<u8>, number: u64, wire: u8) {
+    write_varint(out, (number << 3) | wire as u64)
+}
+
+fn encode_message(map: &Map<String, Value>) -> Result<Vec<u8>, CusError> {
+    let mut out = vec![];
+    for (k, v) in map {
+        let number: u64 = k
+            .parse()
+            .map_err(|_| CusError::App(format!("Invalid protobuf field number {}", k)))?;
+        match v {
+            Value::Array(items) => {
+                for item in items {
+                    encode_field(&mut out, number, item)?;
+                }
+            }
+            v => encode_field(&mut out, number, v)?,
+        }
+    }
+    Ok(out)
+}
+
+fn encode_field(out: &mut Vec<u8>, number: u64, v: &Value) -> Result<(), CusError> {
+    if let Some(b) = as_binary(v) {
+        let b = b?;
+        write_tag(out, number, LEN);
+        write_varint(out, b.len() as u64);
+        out.extend(b);
+        return Ok(());
+    }
+    match v {
+        Value::Bool(b) => {
+            write_tag(out, number, VARINT);
+            write_varint(out, *b as u64);
+        }
+        Value::Number(n) => {
+            // the negative int32 and int64 are written in 10 bytes
+            let v = match (n.as_u64(), n.as_i64()) {
+                (Some(v), _) => v,
+                (_, Some(v)) => v as u64,
+                _ => return Err(CusError::App(format!("Invalid protobuf varint {}", n))),
+            };
+            write_tag(out, number, VARINT);
+            write_varint(out, v);
+        }
+        Value::String(s) => {
+            write_tag(out, number, LEN);
+            write_varint(out, s.len() as u64);
+            out.extend(s.as_bytes());
+        }
+        Value::Object(map) => match (map.get("type").and_then(|t| t.as_str()), map.get("value")) {
+            (Some("fixed64"), Some(v)) if map.len() == 2 => {
+                let v = v
+                    .as_u64()
+                    .ok_or_else(|| CusError::build("Invalid protobuf fixed64"))?;
+                write_tag(out, number, FIXED64);
+                out.extend(v.to_le_bytes());
+            }
+            (Some("fixed32"), Some(v)) if map.len() == 2 => {
+                let v = v
+                    .as_u64()
+                    .and_then(|v| u32::try_from(v).ok())
+                    .ok_or_else(|| CusError::build("Invalid protobuf fixed32"))?;
+                write_tag(out, number, FIXED32);
+                out.extend(v.to_le_bytes());
+            }
+            _ => {
+                let b = encode_message(map)?;
+                write_tag(out, number, LEN);
+                write_varint(out, b.len() as u64);
+                out.extend(b);
+            }
+        },
+        _ => {
+            return Err(CusError::App(format!(
+                "Invalid protobuf value of the field {}",
+                number
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn err(e: impl ToString) -> CusError {
+    CusError::App(e.to_string())
+}
+
+// the .proto sources uploaded by the user, by the import name
+struct Sources(Vec<(String, String)>);
+
+impl FileResolver for Sources {
+    fn resolve_path(&self, path: &Path) -> Option<String> {
+        let name = path.to_str()?;
+        self.0
+            .iter()
+            .any(|(n, _)| n == name)
+            .then(|| name.to_string())
+    }
+    fn open_file(&self, name: &str) -> Result<File, protox::Error> {
+        match self.0.iter().find(|(n, _)| n == name) {
+            Some((n, source)) => File::from_source(n, source),
+            None => Err(protox::Error::file_not_found(name)),
+        }
+    }
+}
+
+// compile the .proto sources to the encoded FileDescriptorSet
+// the well known types can be imported
+pub fn compile(files: &[(String, String)]) -> Result<Vec<u8>, CusError> {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(Sources(files.to_vec()));
+    resolver.add(GoogleFileResolver::new());
+    let mut compiler = protox::Compiler::with_file_resolver(resolver);
+    compiler.include_imports(true);
+    for (name, _) in files {
+        compiler.open_file(name).map_err(err)?;
+    }
+    Ok(compiler.encode_file_descriptor_set())
+}
+
+fn pool(set: &[u8]) -> Result<DescriptorPool, CusError> {
+    if let Ok(pool) = DescriptorPool::decode(set) {
+        return Ok(pool);
+    }
+    // the set compiled by protoc without --include_imports may import the well known types
+    let mut pool = DescriptorPool::global();
+    pool.decode_file_descriptor_set(set).map_err(err)?;
+    Ok(pool)
+}
+
+// the full names of the messages in the descriptor set
+pub fn messages(set: &[u8]) -> Result<Vec<String>, CusError> {
+    Ok(pool(set)?
+        .all_messages()
+        .map(|m| m.full_name().to_string())
+        .collect())
+}
+
+pub fn descriptor(set: &[u8], message: &str) -> Result<MessageDescriptor, CusError> {
+    pool(set)?
+        .get_message_by_name(message)
+        .ok_or_else(|| CusError::App(format!("Message {} not found in the schema", message)))
+}
+
+// decode the message to the pretty json of the proto3 json mapping
+pub fn decode_with(desc: MessageDescriptor, bytes: &[u8]) -> Result<String, CusError> {
+    let message = DynamicMessage::decode(desc, bytes).map_err(err)?;
+    let mut out = vec![];
+    let mut serializer = serde_json::Serializer::pretty(&mut out);
+    // the default values are shown so they can be edited
+    message.serialize_with_options(
+        &mut serializer,
+        &SerializeOptions::new().skip_default_fields(false),
+    )?;
+    Ok(String::from_utf8(out)?)
+}
+
+pub fn encode_with(desc: MessageDescriptor, json: &str) -> Result<Vec<u8>, CusError> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let message = DynamicMessage::deserialize(desc, &mut deserializer)?;
+    deserializer.end()?;
+    Ok(message.encode_to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(v: Value) -> Vec<u8> {
+        let b = Protobuf.encode(&v).unwrap().unwrap();
+        assert_eq!(Protobuf.decode(&b).unwrap(), v);
+        b
+    }
+
+    #[test]
+    fn fields() {
+        let b = [0x08, 0x96, 0x01];
+        assert!(Protobuf.detect(&b));
+        assert_eq!(Protobuf.decode(&b).unwrap(), json!({ "1": 150 }));
+        assert_eq!(round_trip(json!({ "1": 150 })), b);
+        round_trip(json!({
+            "1": "hi",
+            "2": { "1": 1, "2": { "1": 2 } },
+            "3": [1, 2, 3],
+            "4": { "type": "fixed32", "value": 7 },
+            "5": { "type": "fixed64", "value": u64::MAX },
+            "6": { "type": "binary", "encoding": "base64", "value": "AP8=" },
+        }));
+    }
+
+    #[test]
+    fn bool_varint() {
+        // the bool is a varint without the schema
+        let b = Protobuf.encode(&json!({ "1": true })).unwrap().unwrap();
+        assert_eq!(Protobuf.decode(&b).unwrap(), json!({ "1": 1 }));
+    }
+
+    #[test]
+    fn negative_varint() {
+        // the negative int is written as the 64 bits two's complement
+        let b = Protobuf.encode(&json!({ "1": -1 })).unwrap().unwrap();
+        assert_eq!(b.len(), 11);
+        assert_eq!(Protobuf.decode(&b).unwrap(), json!({ "1": u64::MAX }));
+    }
+
+    #[test]
+    fn too_deep() {
+        // every message holds the next one in its field 1
+        let mut b = vec![0x08, 0x01];
+        for _ in 0..10000 {
+            let mut outer = vec![0x0a];
+            write_varint(&mut outer, b.len() as u64);
+            outer.append(&mut b);
+            b = outer;
+        }
+        assert!(Protobuf.detect(&b));
+        let mut v = &Protobuf.decode(&b).unwrap();
+        for _ in 0..=MAX_DEPTH {
+            v = &v["1"];
+        }
+        assert_eq!(v["type"], "binary");
+    }
+
+    #[test]
+    fn malformed() {
+        for b in [
+            &[0x08][..],
+            &[0x00, 0x01],
+            // the wire types of the groups are not supported
+            &[0x0b, 0x0c],
+            &[0x0a, 0x05, b'a'],
+            &[0x0d, 0x01, 0x02],
+        ] {
+            assert!(!Protobuf.detect(b));
+            assert!(Protobuf.decode(b).is_err());
+        }
+        assert!(!Protobuf.detect(&[]));
+        assert!(Protobuf.encode(&json!([1])).unwrap().is_err());
+        assert!(Protobuf.encode(&json!({ "x": 1 })).unwrap().is_err());
+        assert!(Protobuf.encode(&json!({ "1": null })).unwrap().is_err());
+    }
+
+    const USER: &str = r#"
+        syntax = "proto3";
+        package demo;
+        import "google/protobuf/timestamp.proto";
+        import "demo/role.proto";
+        message User {
+            string name = 1;
+            int32 age = 2;
+            bool admin = 3;
+            google.protobuf.Timestamp created = 4;
+            Role role = 5;
+        }
+    "#;
+
+    const ROLE: &str = r#"
+        syntax = "proto3";
+        package demo;
+        enum Role {
+            GUEST = 0;
+            OWNER = 1;
+        }
+    "#;
+
+    fn user_schema() -> Vec<u8> {
+        compile(&[
+            ("demo/user.proto".to_string(), USER.to_string()),
+            ("demo/role.proto".to_string(), ROLE.to_string()),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn schema() {
+        let set = user_schema();
+        let names = messages(&set).unwrap();
+        assert!(names.contains(&"demo.User".to_string()));
+        assert!(names.contains(&"google.protobuf.Timestamp".to_string()));
+        assert!(descriptor(&set, "demo.Missing").is_err());
+        // the import must be uploaded too
+        assert!(compile(&[("demo/user.proto".to_string(), USER.to_string())]).is_err());
+        assert!(compile(&[("bad.proto".to_string(), "message {".to_string())]).is_err());
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let desc = descriptor(&user_schema(), "demo.User").unwrap();
+        let b = [
+            0x0a, 0x02, b'h', b'i', 0x10, 0x07, 0x18, 0x01, 0x22, 0x02, 0x08, 0x01, 0x28, 0x01,
+        ];
+        let pretty = decode_with(desc.clone(), &b).unwrap();
+        let v: Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(
+            v,
+            json!({
+                "name": "hi",
+                "age": 7,
+                "admin": true,
+                "created": "1970-01-01T00:00:01Z",
+                "role": "OWNER",
+            })
+        );
+        assert_eq!(encode_with(desc.clone(), &pretty).unwrap(), b);
+        // the default values are shown, and they are not written back
+        let v: Value = serde_json::from_str(&decode_with(desc.clone(), &[]).unwrap()).unwrap();
+        assert_eq!(v["name"], "");
+        assert_eq!(v["age"], 0);
+        assert_eq!(v["role"], "GUEST");
+        assert!(encode_with(desc.clone(), r#"{"name":"","age":0}"#)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn schema_malformed() {
+        let desc = descriptor(&user_schema(), "demo.User").unwrap();
+        assert!(decode_with(desc.clone(), &[0x0a, 0x05, b'a']).is_err());
+        // the wire type of the field does not match the schema
+        assert!(decode_with(desc.clone(), &[0x0d, 0x01, 0x02, 0x03, 0x04]).is_err());
+        assert!(encode_with(desc.clone(), r#"{"age":"x"}"#).is_err());
+        assert!(encode_with(desc.clone(), r#"{"nope":1}"#).is_err());
+        assert!(encode_with(desc, r#"{"name":"a"} x"#).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use super::Serialization;
+use crate::err::CusError;
+use serde_json::Value;
+
+pub struct Json;
+
+impl Serialization for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        matches!(
+            bytes.iter().find(|b| !b.is_ascii_whitespace()),
+            Some(b'{') | Some(b'[')
+        )
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(serde_json::to_vec(value).map_err(CusError::from))
+    }
+}
+
+// the utf-8 text as it is
+pub struct Text;
+
+impl Serialization for Text {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+    fn detect(&self, bytes: &[u8]) -> bool {
+        std::str::from_utf8(bytes).is_ok()
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        Ok(Value::String(String::from_utf8(bytes.to_vec())?))
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        Some(Ok(match value {
+            Value::String(s) => s.as_bytes().to_vec(),
+            v => v.to_string().into_bytes(),
+        }))
+    }
+    fn plain(&self) -> bool {
+        true
+    }
+}
+
+// the hex dump of any bytes
+pub struct Hex;
+
+impl Serialization for Hex {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+    fn detect(&self, _bytes: &[u8]) -> bool {
+        true
+    }
+    fn decode(&self, bytes: &[u8]) -> Result<Value, CusError> {
+        Ok(Value::String(hex::encode(bytes)))
+    }
+    fn encode(&self, value: &Value) -> Option<Result<Vec<u8>, CusError>> {
+        let s = value.as_str().unwrap_or_default();
+        // the dump may be edited with spaces
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        Some(hex::decode(s).map_err(|e| CusError::App(e.to_string())))
+    }
+    fn plain(&self) -> bool {
+        true
+    }
+}
@@ -1,13 +1,17 @@
 use crate::{
-    connection::{CValue, Node},
+    connection::{
+        resp2,
+        tunnel::{self, AsyncClusterConnection, ClusterSyncConnection, TunnelledConnection},
+        CValue, CommandFlags, Node,
+    },
     err::CusError,
     model::Command,
+    proxy::{self, ProxyParams},
     ssh::{self, SshProxy},
-    utils,
+    tls, utils,
 };
 use chrono::prelude::*;
-use redis::cluster::{ClusterClient, ClusterConnection as RedisSyncClusterConnection};
-use redis::cluster_async::ClusterConnection;
+use redis::cluster::{ClusterClient, ClusterClientBuilder};
 use redis::Arg;
 use redis::Client;
 use redis::Connection as RedisSyncConnection;
@@ -16,63 +20,238 @@ use redis::{
     aio::{ConnectionLike, MultiplexedConnection},
     AsyncConnectionConfig,
 };
+use redis::{ClientTlsConfig, TlsCertificates};
 
 use redis::aio::Monitor;
 use ssh_jumper::model::SshForwarderEnd;
+use std::any::TypeId;
+use std::fs;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::sync::oneshot::Receiver;
 
+#[derive(Clone, Debug)]
+pub struct TlsParams {
+    // pem file paths
+    pub ca: Option<String>,
+    pub cert: Option<String>,
+    pub key: Option<String>,
+    // skip the certificate verification
+    pub insecure: bool,
+    // the name the certificate is checked against, the host if none
+    pub server_name: Option<String>,
+}
+
+impl TlsParams {
+    // read the pem files, none if the system trust store and no client cert are used
+    pub fn get_certificates(&self) -> Result<Option<TlsCertificates>, CusError> {
+        let read = |path: &Option<String>| -> Result<Option<Vec<u8>>, CusError> {
+            match path {
+                Some(p) if !p.is_empty() => Ok(Some(fs::read(p)?)),
+                _ => Ok(None),
+            }
+        };
+        let root_cert = read(&self.ca)?;
+        let cert = read(&self.cert)?;
+        let key = read(&self.key)?;
+        let client_tls = match (cert, key) {
+            (Some(client_cert), Some(client_key)) => Some(ClientTlsConfig {
+                client_cert,
+                client_key,
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(CusError::build(
+                    "Both client certificate and key are required",
+                ))
+            }
+        };
+        if root_cert.is_none() && client_tls.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(TlsCertificates {
+            client_tls,
+            root_cert,
+        }))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ConnectedParam {
     pub tcp_host: String,
     pub tcp_port: u16,
     pub username: Option<String>,
     pub password: Option<String>,
+    pub tls: Option<TlsParams>,
+    pub unix_socket: Option<String>,
+    pub connect_timeout: Option<Duration>,
+    pub response_timeout: Option<Duration>,
+    pub protocol: redis::ProtocolVersion,
+}
+
+impl ConnectedParam {
+    pub fn get_async_config(&self) -> AsyncConnectionConfig {
+        let mut config = AsyncConnectionConfig::new();
+        if let Some(t) = self.connect_timeout {
+            config = config.set_connection_timeout(t);
+        }
+        if let Some(t) = self.response_timeout {
+            config = config.set_response_timeout(t);
+        }
+        config
+    }
+
+    pub fn get_client(self) -> Result<Client, CusError> {
+        if let Some(tls) = &self.tls {
+            if let Some(certs) = tls.get_certificates()? {
+                return Ok(Client::build_with_tls(self, certs)?);
+            }
+        }
+        Ok(Client::open(self)?)
+    }
+
+    pub fn get_cluster_client(self) -> Result<ClusterClient, CusError> {
+        let node = (self.tcp_host.clone(), self.tcp_port);
+        self.get_cluster_client_of(&[node])
+    }
+
+    // the cluster client with the given addresses as the initial nodes
+    pub fn get_cluster_client_of(self, nodes: &[(String, u16)]) -> Result<ClusterClient, CusError> {
+        let mut certs = None;
+        if let Some(tls) = &self.tls {
+            certs = tls.get_certificates()?;
+        }
+        let connect_timeout = self.connect_timeout;
+        let response_timeout = self.response_timeout;
+        let initial_nodes: Vec<ConnectedParam> = nodes
+            .iter()
+            .map(|(host, port)| ConnectedParam {
+                tcp_host: host.clone(),
+                tcp_port: *port,
+                ..self.clone()
+            })
+            .collect();
+        let mut builder = ClusterClientBuilder::new(initial_nodes);
+        if let Some(c) = certs {
+            builder = builder.certs(c);
+        }
+        if let Some(t) = connect_timeout {
+            builder = builder.connection_timeout(t);
+        }
+        if let Some(t) = response_timeout {
+            builder = builder.response_timeout(t);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(unix)]
+fn unix_addr(path: &str) -> redis::RedisResult<redis::ConnectionAddr> {
+    Ok(redis::ConnectionAddr::Unix(path.into()))
+}
+
+#[cfg(not(unix))]
+fn unix_addr(_path: &str) -> redis::RedisResult<redis::ConnectionAddr> {
+    Err(redis::RedisError::from((
+        redis::ErrorKind::InvalidClientConfig,
+        "Unix socket is not supported on this platform",
+    )))
 }
 
 impl redis::IntoConnectionInfo for ConnectedParam {
     fn into_connection_info(self) -> redis::RedisResult<redis::ConnectionInfo> {
+        let addr = if let Some(path) = &self.unix_socket {
+            unix_addr(path)?
+        } else if let Some(tls) = &self.tls {
+            redis::ConnectionAddr::TcpTls {
+                host: self.tcp_host.clone(),
+                port: self.tcp_port,
+                insecure: tls.insecure,
+                tls_params: None,
+            }
+        } else {
+            redis::ConnectionAddr::Tcp(self.tcp_host.clone(), self.tcp_port)
+        };
         Ok(redis::ConnectionInfo {
-            addr: redis::ConnectionAddr::Tcp(self.tcp_host.clone(), self.tcp_port),
+            addr,
             redis: redis::RedisConnectionInfo {
                 db: 0,
                 username: self.username,
                 password: self.password,
-                protocol: redis::ProtocolVersion::RESP2,
+                protocol: self.protocol,
             },
         })
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct SentinelParams {
+    pub master_name: String,
+    pub nodes: Vec<(String, u16)>,
+    pub password: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct ConnectionParams {
     pub redis_params: ConnectedParam,
     pub ssh_params: Option<ssh::SshParams>,
+    // the socks5 or http proxy, with ssh the first ssh host is connected through it
+    pub proxy_params: Option<ProxyParams>,
+    pub sentinel_params: Option<SentinelParams>,
     pub is_cluster: bool,
+    // write commands are rejected
+    pub readonly: bool,
+    // commands need a confirmation
+    pub dangerous: Vec<String>,
 }
 
 pub trait Connectable {
     fn get_params(&self) -> ConnectionParams;
 }
 
+impl Connectable for ConnectionParams {
+    fn get_params(&self) -> ConnectionParams {
+        self.clone()
+    }
+}
+
 pub struct Connection {
     pub params: ConnectionParams,
-    pub cancel_tunnel_rx: Option<Receiver<SshForwarderEnd>>,
+    // a receiver for every hop of the ssh tunnel
+    pub cancel_tunnel_rx: Vec<Receiver<SshForwarderEnd>>,
     pub tunnel_addr: Option<SocketAddr>,
+    // the local port forwarded through the proxy, used if there is no ssh tunnel
+    pub proxy_rx: Option<Receiver<()>>,
+    pub proxy_addr: Option<SocketAddr>,
+    // the cluster nodes behind ssh or a proxy, each is tunnelled by the tunnel manager
+    // a node of the cluster owns the node tunnel it reuses as well
+    pub tunnel_owner: Option<String>,
+    // the node addresses scoped by the owner
+    pub cluster_nodes: Vec<(String, u16)>,
+    // the local port doing the tls of the tunnelled or proxied server
+    pub tls_rx: Option<Receiver<()>>,
+    pub tls_addr: Option<SocketAddr>,
 }
 
 impl SshProxy for Connection {
-    fn store_addr(&mut self, addr: SocketAddr, rx: Receiver<SshForwarderEnd>) {
-        self.cancel_tunnel_rx = Some(rx);
+    fn store_addr(&mut self, addr: SocketAddr, rx: Vec<Receiver<SshForwarderEnd>>) {
+        self.close_tunnel();
+        self.cancel_tunnel_rx = rx;
         self.tunnel_addr = Some(addr);
     }
     fn get_ssh_config(&self) -> Option<ssh::SshParams> {
+        // a unix socket is always local
+        if self.params.redis_params.unix_socket.is_some() {
+            return None;
+        }
         self.params.ssh_params.clone()
     }
+    // close the hops from the last one, each forwarder stops and drops its ssh session
     fn close_tunnel(&mut self) {
-        if let Some(mut rx) = self.cancel_tunnel_rx.take() {
+        while let Some(mut rx) = self.cancel_tunnel_rx.pop() {
             rx.close();
         }
+        self.tunnel_addr = None;
     }
 }
 impl Connectable for Connection {
@@ -83,31 +262,128 @@ impl Connectable for Connection {
 impl Drop for Connection {
     fn drop(&mut self) {
         self.close_tunnel();
+        self.close_proxy();
+        self.close_tls_bridge();
+        if let Some(owner) = self.tunnel_owner.take() {
+            tunnel::release(&owner);
+        }
     }
 }
 impl Connection {
     pub fn new(params: ConnectionParams) -> Self {
         Self {
             params,
-            cancel_tunnel_rx: None,
+            cancel_tunnel_rx: vec![],
             tunnel_addr: None,
+            proxy_rx: None,
+            proxy_addr: None,
+            tunnel_owner: None,
+            cluster_nodes: vec![],
+            tls_rx: None,
+            tls_addr: None,
         }
     }
-    // get the ssl proxy
+    // get the ssl proxy, or the socks5/http proxy endpoint
     pub fn get_proxy(&self) -> Option<String> {
         if let Some(addr) = self.tunnel_addr {
             return Some(format!("{}:{}", addr.ip(), addr.port()));
         }
+        if self.proxy_addr.is_some() {
+            return self.params.proxy_params.as_ref().map(ProxyParams::endpoint);
+        }
         None
     }
+
+    // open the ssh tunnel, or forward a local port through the proxy if there is no ssh
+    // a unix socket is always local
+    // a node of the cluster reuses the node tunnel of the cluster connection, and keeps it open
+    pub async fn open_tunnel(&mut self) -> Result<(), CusError> {
+        if let Some(ssh) = self.get_ssh_config() {
+            let owner = self
+                .tunnel_owner
+                .get_or_insert_with(|| utils::random_str(32))
+                .clone();
+            if let Some(addr) = tunnel::acquire(&owner, &ssh) {
+                self.close_tunnel();
+                self.tunnel_addr = Some(addr);
+                return self.open_tls_bridge().await;
+            }
+        }
+        ssh::create_tunnel(self).await?;
+        if self.tunnel_addr.is_none() && self.params.redis_params.unix_socket.is_none() {
+            if let Some(p) = self.params.proxy_params.clone() {
+                let host = self.params.redis_params.tcp_host.clone();
+                let port = self.params.redis_params.tcp_port;
+                self.close_proxy();
+                let (addr, rx) = proxy::forward(p, host, port).await?;
+                self.proxy_addr = Some(addr);
+                self.proxy_rx = Some(rx);
+            }
+        }
+        self.open_tls_bridge().await
+    }
+
+    fn close_proxy(&mut self) {
+        if let Some(mut rx) = self.proxy_rx.take() {
+            rx.close();
+        }
+        self.proxy_addr = None;
+    }
+
+    // redis checks the certificate against the address it connects to, which is local with a tunnel,
+    // so the tls is done by a bridge checking it against the server name, the original host by default
+    async fn open_tls_bridge(&mut self) -> Result<(), CusError> {
+        self.close_tls_bridge();
+        let tls = match &self.params.redis_params.tls {
+            Some(t) if !t.insecure => t.clone(),
+            _ => return Ok(()),
+        };
+        let addr = match self.tunnel_addr.or(self.proxy_addr) {
+            Some(a) => a,
+            None => return Ok(()),
+        };
+        let name = tls
+            .server_name
+            .clone()
+            .unwrap_or_else(|| self.params.redis_params.tcp_host.clone());
+        let (addr, rx) = tls::bridge(&tls, &name, addr).await?;
+        self.tls_addr = Some(addr);
+        self.tls_rx = Some(rx);
+        Ok(())
+    }
+
+    fn close_tls_bridge(&mut self) {
+        if let Some(mut rx) = self.tls_rx.take() {
+            rx.close();
+        }
+        self.tls_addr = None;
+    }
     // the server is cluster or not
     pub fn is_cluster(&self) -> bool {
         self.params.is_cluster
     }
+    // the master is discovered by sentinel or not
+    pub fn is_sentinel(&self) -> bool {
+        !self.params.is_cluster && self.params.sentinel_params.is_some()
+    }
+    pub fn get_sentinel_master_name(&self) -> Option<String> {
+        self.params
+            .sentinel_params
+            .as_ref()
+            .map(|s| s.master_name.clone())
+    }
     // get the connection host
     pub fn get_host(&self) -> String {
+        if let Some(path) = &self.params.redis_params.unix_socket {
+            return format!("unix://{}", path);
+        }
+        let mut scheme = "redis";
+        if self.params.redis_params.tls.is_some() {
+            scheme = "rediss";
+        }
         format!(
-            "redis://{}:{}",
+            "{}://{}:{}",
+            scheme,
             self.params.redis_params.tcp_host.clone(),
             self.params.redis_params.tcp_port
         )
@@ -115,67 +391,228 @@ impl Connection {
     // get the redis params
     // if proxy set
     // host/port will be replaced
+    // with the tls bridge the client connects to it without tls
     pub fn get_connected_params(&self) -> ConnectedParam {
         let mut params = self.params.redis_params.clone();
-        if let Some(addr) = self.tunnel_addr {
+        if let Some(addr) = self.tls_addr {
+            params.tcp_host = addr.ip().to_string();
+            params.tcp_port = addr.port();
+            params.tls = None;
+        } else if let Some(addr) = self.tunnel_addr.or(self.proxy_addr) {
             params.tcp_host = addr.ip().to_string();
             params.tcp_port = addr.port();
         }
         params
     }
 
+    // connect to the first reachable sentinel node
+    // the returned connection holds the ssh tunnel, keep it until the sentinel is not needed
+    pub async fn get_sentinel(&self) -> Result<(Connection, MultiplexedConnection), CusError> {
+        let sentinel = match &self.params.sentinel_params {
+            Some(s) => s.clone(),
+            None => return Err(CusError::build("Not a Sentinel Server")),
+        };
+        let mut last_err = CusError::build("No sentinel node");
+        for (host, port) in sentinel.nodes {
+            let mut params = self.params.clone();
+            params.sentinel_params = None;
+            params.redis_params.tcp_host = host.clone();
+            params.redis_params.tcp_port = port;
+            params.redis_params.username = None;
+            params.redis_params.password = sentinel.password.clone();
+            params.redis_params.unix_socket = None;
+            if let Some(ssh) = params.ssh_params.as_mut() {
+                ssh.target_host = host.clone();
+                ssh.target_port = port;
+            }
+            let mut node = Connection::new(params);
+            if let Err(e) = node.open_tunnel().await {
+                last_err = e;
+                continue;
+            }
+            let connected_params = node.get_connected_params();
+            let config = connected_params.get_async_config();
+            let client = match connected_params.get_client() {
+                Ok(c) => c,
+                Err(e) => {
+                    last_err = e;
+                    continue;
+                }
+            };
+            match client
+                .get_multiplexed_async_connection_with_config(&config)
+                .await
+            {
+                Ok(conn) => return Ok((node, conn)),
+                Err(e) => last_err = CusError::from(e),
+            }
+        }
+        Err(last_err)
+    }
+
+    // ask the sentinels for the current master, and point the params to it
+    pub async fn resolve_master(&mut self) -> Result<(), CusError> {
+        if !self.is_sentinel() {
+            return Ok(());
+        }
+        let name = self.get_sentinel_master_name().unwrap_or_default();
+        let (_node, mut conn) = self.get_sentinel().await?;
+        let addr: Option<(String, u16)> = redis::cmd("SENTINEL")
+            .arg("GET-MASTER-ADDR-BY-NAME")
+            .arg(&name)
+            .query_async(&mut conn)
+            .await?;
+        match addr {
+            Some((host, port)) => {
+                self.params.redis_params.tcp_host = host.clone();
+                self.params.redis_params.tcp_port = port;
+                if let Some(ssh) = self.params.ssh_params.as_mut() {
+                    ssh.target_host = host;
+                    ssh.target_port = port;
+                }
+                Ok(())
+            }
+            None => Err(CusError::App(format!("Master {} not found", name))),
+        }
+    }
+
+    // the monitor only waits for the connect timeout,
+    // there is no response timeout as it is waiting for the events
     pub async fn get_monitor(&self) -> Result<Monitor, CusError> {
         let params = self.get_connected_params();
-        let client = Client::open(params)?;
-        let result = client.get_async_monitor().await;
+        let connect_timeout = params.connect_timeout;
+        let client = params.get_client()?;
+        let result = match connect_timeout {
+            Some(t) => match tokio::time::timeout(t, client.get_async_monitor()).await {
+                Ok(r) => r,
+                Err(_) => return Err(CusError::App(String::from("Connection Timeout"))),
+            },
+            None => client.get_async_monitor().await,
+        };
         match result {
             Ok(c) => Ok(c),
             Err(e) => Err(CusError::App(e.to_string())),
         }
     }
 
+    // the response timeout is used as the read/write timeout
     pub async fn get_sync_one(&self) -> Result<RedisSyncConnection, CusError> {
         let params = self.get_connected_params();
-        let client = Client::open(params)?;
-        let result = client.get_connection();
+        let connect_timeout = params.connect_timeout;
+        let response_timeout = params.response_timeout;
+        let client = params.get_client()?;
+        let result = match connect_timeout {
+            Some(t) => client.get_connection_with_timeout(t),
+            None => client.get_connection(),
+        };
         match result {
-            Ok(c) => Ok(c),
+            Ok(c) => {
+                c.set_read_timeout(response_timeout)?;
+                c.set_write_timeout(response_timeout)?;
+                Ok(c)
+            }
             Err(e) => Err(CusError::App(e.to_string())),
         }
     }
 
-    pub async fn get_sync_cluster_one(&self) -> Result<RedisSyncClusterConnection, CusError> {
+    pub async fn get_sync_cluster_one(&self) -> Result<ClusterSyncConnection, CusError> {
+        if !self.cluster_nodes.is_empty() {
+            let params = self.params.redis_params.clone();
+            let client = params.get_cluster_client_of(&self.cluster_nodes)?;
+            return match client.get_generic_connection() {
+                Ok(c) => Ok(ClusterSyncConnection::Tunnelled(c)),
+                Err(e) => Err(CusError::App(e.to_string())),
+            };
+        }
         let params: ConnectedParam = self.get_connected_params();
-        let client = ClusterClient::new(vec![params])?;
+        let client = params.get_cluster_client()?;
         let result = client.get_connection();
         match result {
-            Ok(c) => Ok(c),
+            Ok(c) => Ok(ClusterSyncConnection::Direct(c)),
             Err(e) => Err(CusError::App(e.to_string())),
         }
     }
 
-    pub async fn get_normal(
-        &mut self,
-        config: &AsyncConnectionConfig,
-    ) -> Result<MultiplexedConnection, CusError> {
-        ssh::create_tunnel(self).await?;
+    pub async fn get_normal(&mut self) -> Result<MultiplexedConnection, CusError> {
+        self.open_tunnel().await?;
         let params = self.get_connected_params();
-        let client: Client = Client::open(params)?;
+        let config = params.get_async_config();
+        let client: Client = params.get_client()?;
         let conn: MultiplexedConnection = client
-            .get_multiplexed_async_connection_with_config(config)
+            .get_multiplexed_async_connection_with_config(&config)
             .await?;
         Ok(conn)
     }
-    pub async fn get_cluster(&mut self) -> Result<ClusterConnection, CusError> {
-        ssh::create_tunnel(self).await?;
+    // behind ssh or a proxy, the nodes announce the addresses only reachable from there,
+    // so every node is tunnelled and the client connects with the announced addresses
+    pub async fn get_cluster(&mut self) -> Result<AsyncClusterConnection, CusError> {
+        self.open_tunnel().await?;
+        if self.tunnel_addr.is_some() || self.proxy_addr.is_some() {
+            self.open_node_tunnels().await?;
+            let params = self.params.redis_params.clone();
+            let client = params.get_cluster_client_of(&self.cluster_nodes)?;
+            return match client
+                .get_async_generic_connection::<TunnelledConnection>()
+                .await
+            {
+                Ok(connection) => Ok(AsyncClusterConnection::Tunnelled(connection)),
+                Err(e) => Err(CusError::App(e.to_string())),
+            };
+        }
         let params = self.get_connected_params();
-        let client = ClusterClient::new(vec![params])?;
+        let client = params.get_cluster_client()?;
         let r = client.get_async_connection().await;
         match r {
-            Ok(connection) => Ok(connection),
+            Ok(connection) => Ok(AsyncClusterConnection::Direct(connection)),
             Err(_) => Err(CusError::App(String::from("Connection Timeout"))),
         }
     }
+
+    // the ssh chain or the proxy the cluster nodes are reached through
+    fn get_node_route(&self) -> Option<tunnel::Route> {
+        if let Some(ssh) = self.get_ssh_config() {
+            return Some(tunnel::Route::Ssh(ssh));
+        }
+        if self.proxy_addr.is_none() {
+            return None;
+        }
+        self.params.proxy_params.clone().map(tunnel::Route::Proxy)
+    }
+
+    // ask the seed node for the cluster nodes and open a tunnel for each
+    async fn open_node_tunnels(&mut self) -> Result<(), CusError> {
+        let route = match self.get_node_route() {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let params = self.get_connected_params();
+        let config = params.get_async_config();
+        let mut conn = params
+            .get_client()?
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        let nodes: String = redis::cmd("CLUSTER")
+            .arg("NODES")
+            .query_async(&mut conn)
+            .await?;
+        let mut addrs = vec![];
+        for line in nodes.lines().filter(|l| !l.trim().is_empty()) {
+            let node = Node::build(line.to_string(), self.params.clone());
+            // the node without address is disconnected
+            if node.port == 0 || node.host.is_empty() {
+                continue;
+            }
+            addrs.push((node.host, node.port));
+        }
+        let owner = self
+            .tunnel_owner
+            .get_or_insert_with(|| utils::random_str(32))
+            .clone();
+        let tls = self.params.redis_params.tls.clone();
+        tunnel::open(&owner, &route, tls.as_ref(), &addrs).await?;
+        self.cluster_nodes = tunnel::scoped(&owner, &addrs);
+        Ok(())
+    }
 }
 
 pub struct ConnectionWrapper {
@@ -186,6 +623,9 @@ pub struct ConnectionWrapper {
     pub created_at: DateTime<Local>,
     pub model: Connection,
     pub version: Option<String>,
+    pub commands: CommandFlags,
+    // the connection of the blocking commands, created when it is first needed
+    pub blocking: Option<Box<ConnectionWrapper>>,
 }
 
 impl ConnectionWrapper {
@@ -196,7 +636,8 @@ impl ConnectionWrapper {
         if connection.params.is_cluster {
             b = Box::new(connection.get_cluster().await?)
         } else {
-            b = Box::new(connection.get_normal(&AsyncConnectionConfig::new()).await?);
+            connection.resolve_master().await?;
+            b = Box::new(connection.get_normal().await?);
         }
         let r = Self {
             id: utils::random_str(32),
@@ -206,6 +647,8 @@ impl ConnectionWrapper {
             model: connection,
             conn: b,
             version: None,
+            commands: CommandFlags::default(),
+            blocking: None,
         };
         Ok(r)
     }
@@ -218,13 +661,76 @@ impl ConnectionWrapper {
         self.model.is_cluster()
     }
 
+    // re-establish the connection and its ssh tunnel
+    // for sentinel, the master is re-resolved from the sentinels
+    // the selected db and client name are restored
+    pub async fn reconnect(&mut self) -> Result<(), CusError> {
+        let mut connection = Connection::new(self.model.get_params());
+        if connection.params.is_cluster {
+            let mut conn = connection.get_cluster().await?;
+            restore(&mut conn, 0).await?;
+            self.conn = Box::new(conn);
+            self.nodes = vec![];
+        } else {
+            connection.resolve_master().await?;
+            let mut conn = connection.get_normal().await?;
+            restore(&mut conn, self.db).await?;
+            self.conn = Box::new(conn);
+        }
+        self.model = connection;
+        // the blocking connection may point to the old master
+        self.blocking = None;
+        Ok(())
+    }
+
+    // the command can be sent again after the reconnection
+    // a write may have been run before the connection broke, so only the read is retried,
+    // unless the command never reached the server or the demoted sentinel master rejected it
+    // the other errors are returned, the health monitor reconnects
+    fn can_retry(&self, cmd: &redis::Cmd, err: &redis::RedisError) -> bool {
+        #[allow(deprecated)]
+        let not_connected = std::error::Error::cause(err)
+            .and_then(|e| e.downcast_ref::<std::io::Error>())
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::NotConnected);
+        if err.is_connection_refusal() || not_connected {
+            return true;
+        }
+        if self.model.is_sentinel() && err.code() == Some("READONLY") {
+            return true;
+        }
+        (err.is_io_error() || err.is_connection_dropped()) && self.commands.is_read(cmd)
+    }
+
+    // load the flags of the command by COMMAND INFO, once for every command name
+    async fn load_flags(&mut self, cmd: &redis::Cmd) -> Result<(), CusError> {
+        if let Some(name) = self.commands.missing(cmd) {
+            let value: redis::Value = CommandFlags::info_cmd(&name).query_async(self).await?;
+            self.commands.store(&name, &value);
+        }
+        Ok(())
+    }
+
+    // reject the write command on the readonly connection
+    // on the other connections the flags only decide the retry, so the lookup may fail
+    pub async fn check_writable(&mut self, cmd: &redis::Cmd) -> Result<(), CusError> {
+        if !self.model.params.readonly {
+            let _ = self.load_flags(cmd).await;
+            return Ok(());
+        }
+        self.load_flags(cmd).await?;
+        if self.commands.is_write(cmd) {
+            return Err(CusError::Readonly(CommandFlags::name(cmd)));
+        }
+        Ok(())
+    }
+
     // execute the redis command
     pub async fn execute<T>(
         &mut self,
         cmd: &mut redis::Cmd,
     ) -> Result<(T, Command), (CusError, Command)>
     where
-        T: FromRedisValue,
+        T: FromRedisValue + 'static,
     {
         let mut cmd_vec: Vec<String> = vec![];
         for arg in cmd.args_iter() {
@@ -240,8 +746,24 @@ impl ConnectionWrapper {
                 Arg::Cursor => {}
             }
         }
+        if let Err(err) = self.check_writable(cmd).await {
+            let cus_cmd = Command {
+                id: utils::random_str(32),
+                cmd: cmd_vec.join(" "),
+                response: CValue::Str(err.to_string()),
+                created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                host: self.model.get_host(),
+                duration: 0,
+            };
+            return Err((err, cus_cmd));
+        }
         let start = Local::now();
-        let value_r = cmd.query_async(self).await;
+        let mut value_r = cmd.query_async(self).await;
+        if let Err(e) = &value_r {
+            if self.can_retry(cmd, e) && self.reconnect().await.is_ok() {
+                value_r = cmd.query_async(self).await;
+            }
+        }
         let end = Local::now();
         let mut rep: Vec<String> = vec![];
         let mut cus_cmd = Command {
@@ -253,13 +775,19 @@ impl ConnectionWrapper {
             duration: end.timestamp_micros() - start.timestamp_micros(),
         };
         match value_r {
-            Ok(value) => match T::from_redis_value(&value) {
-                Ok(v) => {
-                    cus_cmd.response = CValue::build(value);
-                    Ok((v, cus_cmd))
+            Ok(value) => {
+                cus_cmd.response = CValue::build(value.clone());
+                // CValue keeps the resp3 types, the others are parsed from the resp2 shape
+                let value = if TypeId::of::<T>() == TypeId::of::<CValue>() {
+                    value
+                } else {
+                    resp2(value)
+                };
+                match T::from_redis_value(&value) {
+                    Ok(v) => Ok((v, cus_cmd)),
+                    Err(err) => Err((CusError::App(err.to_string()), cus_cmd)),
                 }
-                Err(err) => Err((CusError::App(err.to_string()), cus_cmd)),
-            },
+            }
             Err(err) => {
                 rep.push(err.to_string());
                 cus_cmd.response = CValue::Str(err.to_string());
@@ -269,6 +797,22 @@ impl ConnectionWrapper {
     }
 }
 
+// select the db and set the client name of a new connection
+async fn restore<C: ConnectionLike>(conn: &mut C, db: u8) -> Result<(), CusError> {
+    if db != 0 {
+        redis::cmd("select")
+            .arg(db)
+            .query_async::<String>(conn)
+            .await?;
+    }
+    let _ = redis::cmd("CLIENT")
+        .arg("SETNAME")
+        .arg("tauri-redis")
+        .query_async::<String>(conn)
+        .await;
+    Ok(())
+}
+
 impl ConnectionLike for ConnectionWrapper {
     fn req_packed_command<'a>(
         &'a mut self,
@@ -0,0 +1,127 @@
+use redis::{Arg, Cmd, FromRedisValue, Value};
+use std::collections::HashMap;
+
+// commands changing the server or running scripts, they are not flagged as write
+const ADMIN_WRITES: [&str; 30] = [
+    "acl|deluser",
+    "acl|load",
+    "acl|setuser",
+    "bgrewriteaof",
+    "bgsave",
+    "client|kill",
+    "client|pause",
+    "cluster|addslots",
+    "cluster|delslots",
+    "cluster|failover",
+    "cluster|forget",
+    "cluster|meet",
+    "cluster|replicate",
+    "cluster|reset",
+    "cluster|setslot",
+    "config|resetstat",
+    "config|rewrite",
+    "config|set",
+    "debug",
+    "eval",
+    "evalsha",
+    "failover",
+    "fcall",
+    "memory|purge",
+    "module|load",
+    "module|unload",
+    "replicaof",
+    "script|flush",
+    "shutdown",
+    "slaveof",
+];
+
+// the write flag of the commands, loaded by COMMAND INFO
+#[derive(Default)]
+pub struct CommandFlags {
+    // command name or container|subcommand => is write
+    cache: HashMap<String, bool>,
+}
+
+impl CommandFlags {
+    // the lowercase command name and subcommand
+    pub fn names(cmd: &Cmd) -> (String, Option<String>) {
+        let mut names = cmd.args_iter().filter_map(|arg| match arg {
+            Arg::Simple(v) => Some(String::from_utf8_lossy(v).to_lowercase()),
+            Arg::Cursor => None,
+        });
+        let name = names.next().unwrap_or_default();
+        (name, names.next())
+    }
+
+    pub fn name(cmd: &Cmd) -> String {
+        Self::names(cmd).0.to_uppercase()
+    }
+
+    // the command name to look up by COMMAND INFO, none if it is cached
+    pub fn missing(&self, cmd: &Cmd) -> Option<String> {
+        let (name, _) = Self::names(cmd);
+        if name.is_empty() || self.cache.contains_key(&name) {
+            return None;
+        }
+        Some(name)
+    }
+
+    // store the reply of COMMAND INFO name
+    // an unknown command is not write, the server will reject it
+    pub fn store(&mut self, name: &str, value: &Value) {
+        self.cache.insert(name.to_string(), false);
+        if let Value::Array(items) = value {
+            for item in items {
+                self.store_entry(item);
+            }
+        }
+    }
+
+    // [name, arity, flags, first key, last key, step, ..., subcommands]
+    fn store_entry(&mut self, value: &Value) {
+        if let Value::Array(fields) = value {
+            let name = match fields.first().map(String::from_redis_value) {
+                Some(Ok(name)) => name.to_lowercase(),
+                _ => return,
+            };
+            let flags: Vec<String> = match fields.get(2) {
+                Some(v) => Vec::from_redis_value(v).unwrap_or_default(),
+                None => vec![],
+            };
+            self.cache.insert(name, flags.iter().any(|f| f == "write"));
+            // redis 7 returns the subcommands of the container command
+            if let Some(Value::Array(subcommands)) = fields.get(9) {
+                for sub in subcommands {
+                    self.store_entry(sub);
+                }
+            }
+        }
+    }
+
+    pub fn is_write(&self, cmd: &Cmd) -> bool {
+        let (name, sub) = Self::names(cmd);
+        if let Some(sub) = sub {
+            let full = format!("{}|{}", name, sub);
+            if ADMIN_WRITES.contains(&full.as_str()) {
+                return true;
+            }
+            if let Some(write) = self.cache.get(&full) {
+                return *write;
+            }
+        }
+        ADMIN_WRITES.contains(&name.as_str()) || self.cache.get(&name).copied().unwrap_or(false)
+    }
+
+    // the command is known and does not write, so it can be sent again
+    pub fn is_read(&self, cmd: &Cmd) -> bool {
+        let (name, _) = Self::names(cmd);
+        self.cache.contains_key(&name) && !self.is_write(cmd)
+    }
+
+    // the command to look up the flags
+    pub fn info_cmd(name: &str) -> Cmd {
+        let mut cmd = redis::cmd("COMMAND");
+        cmd.arg("INFO").arg(name);
+        cmd
+    }
+}
@@ -0,0 +1,73 @@
+use super::CommandFlags;
+use crate::err::CusError;
+use crate::utils;
+use redis::{Arg, Cmd};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as SMutex};
+use std::time::{Duration, Instant};
+
+const TOKEN_TTL: Duration = Duration::from_secs(60);
+
+tokio::task_local! {
+    // the confirmation token sent with the dispatch request
+    pub static CONFIRM_TOKEN: Option<String>;
+}
+
+// the confirmation token of the current request
+pub fn current_token() -> Option<String> {
+    CONFIRM_TOKEN.try_with(|t| t.clone()).ok().flatten()
+}
+
+// the command is in the policy commands or not
+pub fn is_dangerous(cmd: &Cmd, commands: &[String]) -> bool {
+    let (name, sub) = CommandFlags::names(cmd);
+    if commands.contains(&name) {
+        return true;
+    }
+    match sub {
+        Some(sub) => commands.contains(&format!("{}|{}", name, sub)),
+        None => false,
+    }
+}
+
+fn command_string(cmd: &Cmd) -> String {
+    let mut args: Vec<String> = vec![];
+    for arg in cmd.args_iter() {
+        if let Arg::Simple(v) = arg {
+            args.push(String::from_utf8_lossy(v).to_string());
+        }
+    }
+    args.join(" ")
+}
+
+// the issued confirmation tokens
+// a token confirms the same command on the same server once
+#[derive(Default, Clone)]
+pub struct ConfirmStore(Arc<SMutex<HashMap<String, (String, Instant)>>>);
+
+impl ConfirmStore {
+    // the dangerous command without a valid token is rejected with a new token
+    pub fn check(
+        &self,
+        host: &str,
+        cmd: &Cmd,
+        commands: &[String],
+        token: Option<String>,
+    ) -> Result<(), CusError> {
+        if !is_dangerous(cmd, commands) {
+            return Ok(());
+        }
+        let key = format!("{} {}", host, command_string(cmd));
+        let mut tokens = self.0.lock().unwrap();
+        tokens.retain(|_, (_, at)| at.elapsed() < TOKEN_TTL);
+        if let Some(token) = token {
+            if tokens.get(&token).is_some_and(|(k, _)| k == &key) {
+                tokens.remove(&token);
+                return Ok(());
+            }
+        }
+        let token = utils::random_str(32);
+        tokens.insert(token.clone(), (key, Instant::now()));
+        Err(CusError::Confirm(token, CommandFlags::name(cmd)))
+    }
+}
@@ -0,0 +1,98 @@
+use super::ConnectionWrapper;
+use crate::response::EventResp;
+use serde::Serialize;
+use std::sync::Weak;
+use std::time::Duration;
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+
+// the interval of the health check ping, it is not the tcp keepalive of the sockets
+const INTERVAL: Duration = Duration::from_secs(10);
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: u64 = 30;
+
+pub const EVENT_NAME: &str = "connection-state";
+
+#[derive(Serialize, Clone)]
+pub struct ConnectionState {
+    pub cid: u32,
+    // connected, disconnected or reconnecting
+    pub state: String,
+    pub attempt: u32,
+    pub message: Option<String>,
+}
+
+fn emit(window: &Window, cid: u32, state: &str, attempt: u32, message: Option<String>) {
+    let state = ConnectionState {
+        cid,
+        state: state.to_string(),
+        attempt,
+        message,
+    };
+    if let Ok(s) = serde_json::to_string(&EventResp::new(state, EVENT_NAME.to_string())) {
+        let _ = window.emit(EVENT_NAME, s);
+    }
+}
+
+async fn ping(conn: &Mutex<ConnectionWrapper>) -> Result<(), String> {
+    let mut conn = conn.lock().await;
+    let r = tokio::time::timeout(
+        PING_TIMEOUT,
+        redis::cmd("PING").query_async::<String>(&mut *conn),
+    )
+    .await;
+    match r {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(String::from("ping timeout")),
+    }
+}
+
+// reconnect until success, the backoff is doubled every attempt
+// return false if the connection has been closed
+async fn reconnect(cid: u32, conn: &Weak<Mutex<ConnectionWrapper>>, window: &Window) -> bool {
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        let c = match conn.upgrade() {
+            Some(c) => c,
+            None => return false,
+        };
+        emit(window, cid, "reconnecting", attempt, None);
+        let r = c.lock().await.reconnect().await;
+        drop(c);
+        match r {
+            Ok(_) => {
+                emit(window, cid, "connected", attempt, None);
+                return true;
+            }
+            Err(e) => {
+                emit(window, cid, "disconnected", attempt, Some(e.to_string()));
+            }
+        }
+        let backoff = 2u64.saturating_pow(attempt - 1).min(MAX_BACKOFF);
+        tokio::time::sleep(Duration::from_secs(backoff)).await;
+    }
+}
+
+// ping the connection every interval, and re-establish it when broken
+// the task stops when the connection is removed from the manager
+pub fn watch(cid: u32, conn: Weak<Mutex<ConnectionWrapper>>, window: Window) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(INTERVAL).await;
+            let c = match conn.upgrade() {
+                Some(c) => c,
+                None => break,
+            };
+            let r = ping(&c).await;
+            drop(c);
+            if let Err(message) = r {
+                emit(&window, cid, "disconnected", 0, Some(message));
+                if !reconnect(cid, &conn, &window).await {
+                    break;
+                }
+            }
+        }
+    });
+}
@@ -1,21 +1,26 @@
 use crate::{
-    connection::{Connectable, ConnectionWrapper, Node},
+    connection::{
+        guard, resp2, tunnel::ClusterSyncConnection, ConfirmStore, Connectable, Connection,
+        ConnectionWrapper, Node,
+    },
     err::CusError,
     model::Command,
     response::{self, Field},
 };
-use redis::cluster::ClusterConnection as RedisSyncClusterConnection;
 use redis::{Cmd, Connection as RedisSyncConnection};
 use redis::{FromRedisValue, Value};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::{mpsc::Sender, Mutex};
 
 /**
  * connection manager state
+ * every connection has its own lock, the map lock is only held for lookup
  */
 pub struct Manager {
-    pub map: Mutex<HashMap<u32, ConnectionWrapper>>,
+    map: Mutex<HashMap<u32, Arc<Mutex<ConnectionWrapper>>>>,
     debug_tx: Mutex<Vec<Sender<Command>>>,
+    confirms: ConfirmStore,
 }
 
 impl Manager {
@@ -23,12 +28,21 @@ impl Manager {
         Manager {
             map: Mutex::new(HashMap::new()),
             debug_tx: Mutex::new(vec![]),
+            confirms: ConfirmStore::default(),
         }
     }
-    pub async fn add(&self, id: u32, conn: ConnectionWrapper) {
-        self.map.lock().await.insert(id, conn);
-        if let Some(conn) = self.map.lock().await.get_mut(&id) {
-            let _ = self.set_name(conn, "tauri-redis".to_string()).await;
+    pub async fn add(&self, id: u32, mut conn: ConnectionWrapper) -> Arc<Mutex<ConnectionWrapper>> {
+        let _ = self.set_name(&mut conn, "tauri-redis".to_string()).await;
+        let conn = Arc::new(Mutex::new(conn));
+        self.map.lock().await.insert(id, conn.clone());
+        conn
+    }
+
+    // get the connection by id
+    pub async fn get(&self, id: u32) -> Result<Arc<Mutex<ConnectionWrapper>>, CusError> {
+        match self.map.lock().await.get(&id) {
+            Some(conn) => Ok(conn.clone()),
+            None => Err(CusError::connection_not_found()),
         }
     }
 
@@ -42,10 +56,9 @@ impl Manager {
     }
 
     pub async fn get_config(&self, id: u32, pattern: &str) -> Result<Vec<Field>, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&id) {
-            return self.get_config_with(pattern, conn).await;
-        }
-        Err(CusError::connection_not_found())
+        let conn = self.get(id).await?;
+        let mut conn = conn.lock().await;
+        self.get_config_with(pattern, &mut conn).await
     }
 
     pub async fn get_config_with(
@@ -60,10 +73,9 @@ impl Manager {
     }
 
     pub async fn get_version(&self, id: u32) -> Result<String, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&id) {
-            return self.get_version_with(conn).await;
-        }
-        Err(CusError::connection_not_found())
+        let conn = self.get(id).await?;
+        let mut conn = conn.lock().await;
+        self.get_version_with(&mut conn).await
     }
 
     // get redis server version
@@ -88,10 +100,9 @@ impl Manager {
         &self,
         id: u32,
     ) -> Result<HashMap<String, HashMap<String, String>>, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&id) {
-            return self.get_info_with(conn).await;
-        }
-        Err(CusError::connection_not_found())
+        let conn = self.get(id).await?;
+        let mut conn = conn.lock().await;
+        self.get_info_with(&mut conn).await
     }
 
     // get the server info
@@ -143,10 +154,9 @@ impl Manager {
 
     // get cluster server nodes
     pub async fn get_nodes(&self, id: u32) -> Result<Vec<Node>, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&id) {
-            return self.get_nodes_with(conn).await;
-        }
-        Err(CusError::reopen())
+        let conn = self.get(id).await.map_err(|_| CusError::reopen())?;
+        let mut conn = conn.lock().await;
+        self.get_nodes_with(&mut conn).await
     }
 
     // get cluster server nodes
@@ -183,8 +193,15 @@ impl Manager {
         conn: &mut ConnectionWrapper,
     ) -> Result<T, CusError>
     where
-        T: FromRedisValue,
+        T: FromRedisValue + 'static,
     {
+        // the command of the connection policy needs a confirmation token
+        self.confirms.check(
+            &conn.get_host(),
+            cmd,
+            &conn.model.params.dangerous,
+            guard::current_token(),
+        )?;
         let result: Result<(T, Command), (CusError, Command)> = conn.execute::<T>(cmd).await;
         match result {
             Ok((value, cmd)) => {
@@ -204,52 +221,132 @@ impl Manager {
     // execute redis cmd with cid
     pub async fn execute<T>(&self, cid: u32, cmd: &mut Cmd, db: Option<u8>) -> Result<T, CusError>
     where
-        T: FromRedisValue,
+        T: FromRedisValue + 'static,
     {
-        if let Some(conn) = self.map.lock().await.get_mut(&cid) {
-            if !conn.is_cluster() {
-                if let Some(database) = db {
-                    if database != conn.db {
-                        self
-                            .execute_with::<String>(redis::cmd("select").arg(db), conn)
-                            .await?;
-                        conn.db = database
-                    }
+        let conn = self.get(cid).await?;
+        let mut conn = conn.lock().await;
+        self.select_with(db, &mut conn).await?;
+        self.execute_with::<T>(cmd, &mut conn).await
+    }
+
+    // select the db if it is not the current one
+    async fn select_with(
+        &self,
+        db: Option<u8>,
+        conn: &mut ConnectionWrapper,
+    ) -> Result<(), CusError> {
+        if !conn.is_cluster() {
+            if let Some(database) = db {
+                if database != conn.db {
+                    self.execute_with::<String>(redis::cmd("select").arg(db), conn)
+                        .await?;
+                    conn.db = database
                 }
             }
-            return self.execute_with::<T>(cmd, conn).await;
         }
-        Err(CusError::connection_not_found())
+        Ok(())
+    }
+
+    // execute blocking redis cmd (BLPOP, BZPOPMAX...) on a dedicated connection
+    // so the shared connection is not held while the server is waiting
+    // the connection is taken out while the command runs, another blocking command builds its own
+    // when the request is cancelled, the connection is dropped and the server releases the client
+    // it is kept for the next command only if this one succeeds
+    // the response timeout is not applied, the command has its own timeout
+    pub async fn execute_blocking<T>(
+        &self,
+        cid: u32,
+        cmd: &mut Cmd,
+        db: Option<u8>,
+    ) -> Result<T, CusError>
+    where
+        T: FromRedisValue + 'static,
+    {
+        let wrapper = self.get(cid).await?;
+        let cached = wrapper.lock().await.blocking.take();
+        let mut conn = match cached {
+            Some(conn) => conn,
+            None => {
+                let mut params = wrapper.lock().await.model.get_params();
+                params.redis_params.response_timeout = None;
+                let mut conn = ConnectionWrapper::build(params).await?;
+                let _ = self.set_name(&mut conn, "tauri-redis".to_string()).await;
+                Box::new(conn)
+            }
+        };
+        self.select_with(db, &mut conn).await?;
+        let r = self.execute_with::<T>(cmd, &mut conn).await;
+        if r.is_ok() {
+            wrapper.lock().await.blocking.get_or_insert(conn);
+        }
+        r
+    }
+
+    // execute redis cmd on a sentinel node of the connection
+    pub async fn execute_sentinel<T>(&self, cid: u32, cmd: &mut Cmd) -> Result<T, CusError>
+    where
+        T: FromRedisValue + 'static,
+    {
+        let params = self.get(cid).await?.lock().await.model.get_params();
+        let (_node, mut sentinel) = Connection::new(params).get_sentinel().await?;
+        let value: Value = cmd.query_async(&mut sentinel).await?;
+        Ok(T::from_redis_value(&resp2(value))?)
+    }
+
+    // get the master name of the sentinel connection
+    pub async fn get_sentinel_master_name(&self, cid: u32) -> Result<String, CusError> {
+        let conn = self.get(cid).await?;
+        let name = conn.lock().await.model.get_sentinel_master_name();
+        match name {
+            Some(name) => Ok(name),
+            None => Err(CusError::build("Not a Sentinel Server")),
+        }
     }
 
     pub async fn get_is_cluster(&self, cid: u32) -> bool {
-        if let Some(conn) = self.map.lock().await.get_mut(&cid) {
-            return conn.is_cluster();
+        if let Ok(conn) = self.get(cid).await {
+            return conn.lock().await.is_cluster();
         }
         false
     }
 
-    pub async fn get_sync_conn(&self, cid: u32) -> Result<RedisSyncConnection, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&cid) {
-            return conn.model.get_sync_one().await;
-        }
-        Err(CusError::connection_not_found())
+    pub fn get_confirms(&self) -> ConfirmStore {
+        self.confirms.clone()
     }
 
-    pub async fn get_sync_cluster_conn(
-        &self,
-        cid: u32,
-    ) -> Result<RedisSyncClusterConnection, CusError> {
-        if let Some(conn) = self.map.lock().await.get_mut(&cid) {
-            return conn.model.get_sync_cluster_one().await;
+    // the host and the commands need a confirmation
+    pub async fn get_dangerous(&self, cid: u32) -> Result<(String, Vec<String>), CusError> {
+        let conn = self.get(cid).await?;
+        let conn = conn.lock().await;
+        Ok((conn.get_host(), conn.model.params.dangerous.clone()))
+    }
+
+    pub async fn get_is_readonly(&self, cid: u32) -> bool {
+        if let Ok(conn) = self.get(cid).await {
+            return conn.lock().await.model.params.readonly;
         }
-        Err(CusError::connection_not_found())
+        false
+    }
+
+    pub async fn get_sync_conn(&self, cid: u32) -> Result<RedisSyncConnection, CusError> {
+        let conn = self.get(cid).await?;
+        let conn = conn.lock().await;
+        conn.model.get_sync_one().await
+    }
+
+    pub async fn get_sync_cluster_conn(&self, cid: u32) -> Result<ClusterSyncConnection, CusError> {
+        let conn = self.get(cid).await?;
+        let conn = conn.lock().await;
+        conn.model.get_sync_cluster_one().await
     }
 
     // get connected connections info
     pub async fn get_conns(&self) -> Vec<response::Conn> {
         let mut vec = vec![];
-        for (_, v) in self.map.lock().await.iter() {
+        let conns: Vec<Arc<Mutex<ConnectionWrapper>>> =
+            self.map.lock().await.values().cloned().collect();
+        for c in conns {
+            let v = c.lock().await;
             vec.push(response::Conn {
                 id: v.id.clone(),
                 host: v.model.get_host(),
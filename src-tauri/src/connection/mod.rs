@@ -1,22 +1,50 @@
 use redis::{FromRedisValue, RedisResult, Value as RedisValue};
+use serde::ser::SerializeStruct;
 mod conn;
 mod event;
+mod flags;
+mod guard;
+mod health;
 mod manager;
 mod node;
+mod tunnel;
 
-pub use conn::{Connectable, ConnectedParam, Connection, ConnectionParams, ConnectionWrapper};
+pub use conn::{
+    Connectable, ConnectedParam, Connection, ConnectionParams, ConnectionWrapper, SentinelParams,
+    TlsParams,
+};
 pub use event::EventManager;
+pub use flags::CommandFlags;
+pub use guard::{ConfirmStore, CONFIRM_TOKEN};
+pub use health::watch;
 pub use manager::Manager;
 pub use node::Node;
 #[derive(Clone, Debug)]
 pub enum CValue {
     Str(String),
+    // the bulk string is not utf-8
+    Binary(Vec<u8>),
     Vec(Vec<CValue>),
     Int(i64),
     Float(f64),
     Nil,
     Bool(bool),
     Map(Vec<(CValue, CValue)>),
+    // the resp3 types, serialized with the type so the reply can be shown as it is
+    Set(Vec<CValue>),
+    BigNumber(String),
+    Verbatim {
+        format: String,
+        text: String,
+    },
+    Attribute {
+        data: Box<CValue>,
+        attributes: Vec<(CValue, CValue)>,
+    },
+    Push {
+        kind: String,
+        data: Vec<CValue>,
+    },
 }
 impl FromRedisValue for CValue {
     fn from_redis_value(v: &RedisValue) -> RedisResult<Self> {
@@ -31,12 +59,46 @@ impl serde::Serialize for CValue {
     {
         match &self {
             CValue::Str(s) => serializer.serialize_str(s),
+            CValue::Binary(v) => crate::binary::serialize(v, serializer),
             CValue::Vec(v) => v.serialize(serializer),
             CValue::Int(v) => v.serialize(serializer),
             CValue::Nil => serializer.serialize_none(),
             CValue::Float(v) => v.serialize(serializer),
             CValue::Bool(v) => v.serialize(serializer),
             CValue::Map(v) => v.serialize(serializer),
+            CValue::Set(v) => {
+                let mut s = serializer.serialize_struct("set", 2)?;
+                s.serialize_field("type", "set")?;
+                s.serialize_field("value", v)?;
+                s.end()
+            }
+            CValue::BigNumber(v) => {
+                let mut s = serializer.serialize_struct("big_number", 2)?;
+                s.serialize_field("type", "big_number")?;
+                s.serialize_field("value", v)?;
+                s.end()
+            }
+            CValue::Verbatim { format, text } => {
+                let mut s = serializer.serialize_struct("verbatim", 3)?;
+                s.serialize_field("type", "verbatim")?;
+                s.serialize_field("format", format)?;
+                s.serialize_field("value", text)?;
+                s.end()
+            }
+            CValue::Attribute { data, attributes } => {
+                let mut s = serializer.serialize_struct("attribute", 3)?;
+                s.serialize_field("type", "attribute")?;
+                s.serialize_field("attributes", attributes)?;
+                s.serialize_field("value", data)?;
+                s.end()
+            }
+            CValue::Push { kind, data } => {
+                let mut s = serializer.serialize_struct("push", 3)?;
+                s.serialize_field("type", "push")?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("value", data)?;
+                s.end()
+            }
         }
     }
 }
@@ -46,16 +108,10 @@ impl CValue {
         println!("{:?}", v);
         match v {
             RedisValue::Okay => Self::Str("Ok".to_string()),
-            RedisValue::BulkString(s) => {
-                let result = String::from_utf8(s.clone());
-                match result {
-                    Ok(ss) => Self::Str(ss),
-                    Err(_) => {
-                        let ss = String::from_utf8_lossy(&s).to_string();
-                        Self::Str(ss)
-                    }
-                }
-            }
+            RedisValue::BulkString(s) => match String::from_utf8(s) {
+                Ok(ss) => Self::Str(ss),
+                Err(e) => Self::Binary(e.into_bytes()),
+            },
             RedisValue::Array(v) => {
                 let mut vec = vec![];
                 for x in v {
@@ -73,23 +129,47 @@ impl CValue {
                 }
                 Self::Map(vec)
             }
-            // RedisValue::Attribute { data, attributes } => todo!(),
-            RedisValue::Set(v) => {
-                let mut vec = vec![];
-                for x in v {
-                    vec.push(Self::build(x));
-                }
-                Self::Vec(vec)
-            }
+            RedisValue::Attribute { data, attributes } => Self::Attribute {
+                data: Box::new(Self::build(*data)),
+                attributes: attributes
+                    .into_iter()
+                    .map(|(x, y)| (Self::build(x), Self::build(y)))
+                    .collect(),
+            },
+            RedisValue::Set(v) => Self::Set(v.into_iter().map(Self::build).collect()),
             RedisValue::Double(v) => Self::Float(v),
             RedisValue::Boolean(v) => Self::Bool(v),
-            RedisValue::VerbatimString { format: _format, text } => Self::Str(text),
-            RedisValue::BigNumber(big_int) => Self::Str(big_int.to_string()),
-            // RedisValue::Push { kind, data } => todo!(),
+            RedisValue::VerbatimString { format, text } => Self::Verbatim {
+                format: format.to_string(),
+                text,
+            },
+            RedisValue::BigNumber(big_int) => Self::BigNumber(big_int.to_string()),
+            RedisValue::Push { kind, data } => Self::Push {
+                kind: kind.to_string(),
+                data: data.into_iter().map(Self::build).collect(),
+            },
             RedisValue::ServerError(server_error) => Self::Str(server_error.code().to_string()),
-            _ => {
-                CValue::Nil
-            }
         }
     }
 }
+
+// convert the resp3 value to the resp2 shape, so the replies are parsed the same for both protocols
+// maps are flattened to arrays, verbatim strings, doubles and big numbers are bulk strings
+pub fn resp2(v: RedisValue) -> RedisValue {
+    match v {
+        RedisValue::Array(v) | RedisValue::Set(v) | RedisValue::Push { data: v, .. } => {
+            RedisValue::Array(v.into_iter().map(resp2).collect())
+        }
+        RedisValue::Map(v) => RedisValue::Array(
+            v.into_iter()
+                .flat_map(|(x, y)| [resp2(x), resp2(y)])
+                .collect(),
+        ),
+        RedisValue::Attribute { data, .. } => resp2(*data),
+        RedisValue::VerbatimString { text, .. } => RedisValue::BulkString(text.into_bytes()),
+        RedisValue::Double(v) => RedisValue::BulkString(v.to_string().into_bytes()),
+        RedisValue::BigNumber(v) => RedisValue::BulkString(v.to_string().into_bytes()),
+        RedisValue::Boolean(v) => RedisValue::Int(v as i64),
+        v => v,
+    }
+}
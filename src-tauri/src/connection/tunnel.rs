@@ -0,0 +1,516 @@
+use crate::{
+    connection::TlsParams,
+    err::CusError,
+    proxy::{self, ProxyParams},
+    ssh::{self, SshParams, SshProxy},
+    tls,
+};
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::cluster::ClusterConnection as SyncClusterConnection;
+use redis::cluster_async::ClusterConnection;
+use redis::{
+    Cmd, ConnectionAddr, ConnectionInfo, ErrorKind, IntoConnectionInfo, Pipeline, RedisError,
+    RedisFuture, RedisResult, Value,
+};
+use ssh_jumper::model::SshForwarderEnd;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Mutex as SMutex;
+use std::time::Duration;
+use tokio::sync::oneshot::Receiver;
+
+// how the cluster nodes are reached, through the ssh chain or the proxy
+pub enum Route {
+    Ssh(SshParams),
+    Proxy(ProxyParams),
+}
+
+impl Route {
+    // the identity of the ssh chain or the proxy
+    fn key(&self) -> String {
+        match self {
+            Route::Ssh(ssh) => {
+                let mut hops = vec![format!("{}@{}:{}", ssh.username, ssh.host, ssh.port)];
+                for hop in &ssh.jumps {
+                    hops.push(format!("{}@{}:{}", hop.username, hop.host, hop.port));
+                }
+                hops.join(">")
+            }
+            Route::Proxy(p) => format!(
+                "{}@{}",
+                p.username.clone().unwrap_or_default(),
+                p.endpoint()
+            ),
+        }
+    }
+}
+
+// the ssh tunnel or the proxy forwarder of a cluster node
+struct NodeTunnel {
+    // the ssh chain or the proxy the tunnel goes through
+    chain: String,
+    ssh: Option<SshParams>,
+    addr: Option<SocketAddr>,
+    rx: Vec<Receiver<SshForwarderEnd>>,
+    proxy_rx: Option<Receiver<()>>,
+    // the local port doing the tls of the node, checked against the server name or the node host
+    tls_addr: Option<SocketAddr>,
+    tls_rx: Option<Receiver<()>>,
+    // the cluster connections using the tunnel
+    owners: HashSet<String>,
+}
+
+impl SshProxy for NodeTunnel {
+    fn store_addr(&mut self, addr: SocketAddr, rx: Vec<Receiver<SshForwarderEnd>>) {
+        self.addr = Some(addr);
+        self.rx = rx;
+    }
+    fn get_ssh_config(&self) -> Option<SshParams> {
+        self.ssh.clone()
+    }
+    fn close_tunnel(&mut self) {
+        while let Some(mut rx) = self.rx.pop() {
+            rx.close();
+        }
+        if let Some(mut rx) = self.proxy_rx.take() {
+            rx.close();
+        }
+        self.addr = None;
+    }
+}
+
+impl Drop for NodeTunnel {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.tls_rx.take() {
+            rx.close();
+        }
+        self.close_tunnel();
+    }
+}
+
+// the node tunnels are shared by the route and the address announced by the cluster,
+// and each cluster connection knows its route, so the clusters behind different hosts
+// may announce the same private addresses
+#[derive(Default)]
+struct Registry {
+    tunnels: HashMap<String, NodeTunnel>,
+    // the route of every owner
+    routes: HashMap<String, String>,
+}
+
+static TUNNELS: Lazy<SMutex<Registry>> = Lazy::new(Default::default);
+
+fn node_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host, port)
+}
+
+fn tunnel_key(chain: &str, host: &str, port: u16) -> String {
+    format!("{}>{}", chain, node_key(host, port))
+}
+
+// the redis cluster client only knows the node address when it follows the slots or MOVED,
+// so the announced host is prefixed by the owner to find the tunnel of the right cluster
+fn scope(owner: &str, host: &str) -> String {
+    format!("{}.{}", owner, host)
+}
+
+// the owner and the announced host of the scoped host, the owner has no dot
+fn unscope(host: &str) -> Option<(&str, &str)> {
+    host.split_once('.')
+}
+
+// the node addresses the cluster client of the owner connects to
+pub fn scoped(owner: &str, nodes: &[(String, u16)]) -> Vec<(String, u16)> {
+    nodes
+        .iter()
+        .map(|(host, port)| (scope(owner, host), *port))
+        .collect()
+}
+
+// open a tunnel for every node address the route has not tunnelled yet
+// with tls the node is bridged unless the verification is skipped
+pub async fn open(
+    owner: &str,
+    route: &Route,
+    tls_params: Option<&TlsParams>,
+    nodes: &[(String, u16)],
+) -> Result<(), CusError> {
+    let chain = route.key();
+    TUNNELS
+        .lock()
+        .unwrap()
+        .routes
+        .insert(owner.to_string(), chain.clone());
+    for (host, port) in nodes {
+        let key = tunnel_key(&chain, host, *port);
+        if share(&key, owner).is_some() {
+            continue;
+        }
+        let mut tunnel = NodeTunnel {
+            chain: chain.clone(),
+            ssh: None,
+            addr: None,
+            rx: vec![],
+            proxy_rx: None,
+            tls_addr: None,
+            tls_rx: None,
+            owners: HashSet::from([owner.to_string()]),
+        };
+        match route {
+            Route::Ssh(ssh) => {
+                let mut params = ssh.clone();
+                params.target_host = host.clone();
+                params.target_port = *port;
+                tunnel.ssh = Some(params);
+                ssh::create_tunnel(&mut tunnel).await?;
+            }
+            Route::Proxy(p) => {
+                let (addr, rx) = proxy::forward(p.clone(), host.clone(), *port).await?;
+                tunnel.addr = Some(addr);
+                tunnel.proxy_rx = Some(rx);
+            }
+        }
+        if let (Some(t), Some(addr)) = (tls_params.filter(|t| !t.insecure), tunnel.addr) {
+            let name = t.server_name.clone().unwrap_or_else(|| host.clone());
+            let (addr, rx) = tls::bridge(t, &name, addr).await?;
+            tunnel.tls_addr = Some(addr);
+            tunnel.tls_rx = Some(rx);
+        }
+        // another connection may open the same node meanwhile, the first one is kept
+        if share(&key, owner).is_none() {
+            TUNNELS.lock().unwrap().tunnels.insert(key, tunnel);
+        }
+    }
+    Ok(())
+}
+
+// add the owner to the existing tunnel, the local address of the tunnel is returned
+fn share(key: &str, owner: &str) -> Option<SocketAddr> {
+    let mut registry = TUNNELS.lock().unwrap();
+    let tunnel = registry.tunnels.get_mut(key)?;
+    tunnel.owners.insert(owner.to_string());
+    tunnel.addr
+}
+
+// the node tunnel through the chain, the owner keeps it open until it is released
+pub fn acquire(owner: &str, ssh: &SshParams) -> Option<SocketAddr> {
+    let chain = Route::Ssh(ssh.clone()).key();
+    share(
+        &tunnel_key(&chain, &ssh.target_host, ssh.target_port),
+        owner,
+    )
+}
+
+// release the tunnels of the owner, the tunnels without owner are closed
+pub fn release(owner: &str) {
+    let mut registry = TUNNELS.lock().unwrap();
+    registry.routes.remove(owner);
+    registry.tunnels.retain(|_, t| {
+        t.owners.remove(owner);
+        !t.owners.is_empty()
+    });
+}
+
+// replace the scoped address of the node by the local address of its tunnel
+// the owner of the address is returned to scope the addresses in the replies
+fn tunnelled(mut info: ConnectionInfo) -> RedisResult<(ConnectionInfo, String)> {
+    let (host, port) = match &mut info.addr {
+        ConnectionAddr::Tcp(host, port) => (host, port),
+        ConnectionAddr::TcpTls { host, port, .. } => (host, port),
+        ConnectionAddr::Unix(_) => {
+            return Err(RedisError::from((
+                ErrorKind::InvalidClientConfig,
+                "No tunnel for the unix socket",
+            )))
+        }
+    };
+    let tunnel = unscope(host).and_then(|(owner, announced)| {
+        let registry = TUNNELS.lock().unwrap();
+        let chain = registry.routes.get(owner)?;
+        let t = registry.tunnels.get(&tunnel_key(chain, announced, *port))?;
+        Some((owner.to_string(), t.addr, t.tls_addr))
+    });
+    match tunnel {
+        // the bridge does the tls, so the client connects to it without tls
+        Some((owner, _, Some(addr))) => {
+            info.addr = ConnectionAddr::Tcp(addr.ip().to_string(), addr.port());
+            Ok((info, owner))
+        }
+        Some((owner, Some(addr), None)) => {
+            *host = addr.ip().to_string();
+            *port = addr.port();
+            Ok((info, owner))
+        }
+        _ => Err(RedisError::from((
+            ErrorKind::IoError,
+            "No tunnel for the cluster node",
+            node_key(host, *port),
+        ))),
+    }
+}
+
+// the redirection to the announced address is scoped by the owner
+fn scope_err(owner: &str, err: RedisError) -> RedisError {
+    let detail = match err.redirect_node() {
+        Some((addr, slot)) => match addr.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() => {
+                format!("{} {}:{}", slot, scope(owner, host), port)
+            }
+            _ => return err,
+        },
+        None => return err,
+    };
+    RedisError::from((err.kind(), "An error was signalled by the server", detail))
+}
+
+// a redirection replied as a value is returned as the error, as the cluster client does
+fn scope_value(owner: &str, value: Value) -> RedisResult<Value> {
+    match value {
+        Value::ServerError(e) => {
+            let err = RedisError::from(e.clone());
+            if err.redirect_node().is_some() {
+                Err(scope_err(owner, err))
+            } else {
+                Ok(Value::ServerError(e))
+            }
+        }
+        v => Ok(v),
+    }
+}
+
+// the node hosts of the CLUSTER SLOTS reply are scoped by the owner
+// the empty host means the answering node, whose address is already scoped
+fn scope_slots(owner: &str, value: Value) -> Value {
+    let Value::Array(mut slots) = value else {
+        return value;
+    };
+    for slot in slots.iter_mut() {
+        let Value::Array(items) = slot else {
+            continue;
+        };
+        for node in items.iter_mut().skip(2) {
+            if let Value::Array(node) = node {
+                if let Some(Value::BulkString(host)) = node.first_mut() {
+                    if !host.is_empty() && host.as_slice() != b"?" {
+                        *host = scope(owner, &String::from_utf8_lossy(host)).into_bytes();
+                    }
+                }
+            }
+        }
+    }
+    Value::Array(slots)
+}
+
+fn is_slots(packed: &[u8]) -> bool {
+    static SLOTS: Lazy<Vec<u8>> =
+        Lazy::new(|| redis::cmd("CLUSTER").arg("SLOTS").get_packed_command());
+    packed == SLOTS.as_slice()
+}
+
+// scope the addresses of the reply, the slots reply has the node hosts
+fn scope_reply(owner: &str, slots: bool, reply: RedisResult<Value>) -> RedisResult<Value> {
+    match reply {
+        Ok(v) if slots => Ok(scope_slots(owner, v)),
+        Ok(v) => scope_value(owner, v),
+        Err(e) => Err(scope_err(owner, e)),
+    }
+}
+
+fn scope_replies(owner: &str, replies: RedisResult<Vec<Value>>) -> RedisResult<Vec<Value>> {
+    match replies {
+        Ok(values) => values.into_iter().map(|v| scope_value(owner, v)).collect(),
+        Err(e) => Err(scope_err(owner, e)),
+    }
+}
+
+// the async connection of the cluster node behind ssh or a proxy
+// the node addresses in the replies are scoped by the owner of the connection
+#[derive(Clone)]
+pub struct TunnelledConnection {
+    conn: MultiplexedConnection,
+    owner: String,
+}
+
+impl ConnectionLike for TunnelledConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        async move {
+            let reply = self.conn.req_packed_command(cmd).await;
+            // only an array reply is packed again to check the command
+            let slots = matches!(reply, Ok(Value::Array(_))) && is_slots(&cmd.get_packed_command());
+            scope_reply(&self.owner, slots, reply)
+        }
+        .boxed()
+    }
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        async move {
+            let replies = self.conn.req_packed_commands(cmd, offset, count).await;
+            scope_replies(&self.owner, replies)
+        }
+        .boxed()
+    }
+    fn get_db(&self) -> i64 {
+        self.conn.get_db()
+    }
+}
+
+impl redis::cluster_async::Connect for TunnelledConnection {
+    fn connect<'a, T>(
+        info: T,
+        response_timeout: Duration,
+        connection_timeout: Duration,
+    ) -> RedisFuture<'a, Self>
+    where
+        T: IntoConnectionInfo + Send + 'a,
+    {
+        async move {
+            let (info, owner) = tunnelled(info.into_connection_info()?)?;
+            let conn = <MultiplexedConnection as redis::cluster_async::Connect>::connect(
+                info,
+                response_timeout,
+                connection_timeout,
+            )
+            .await?;
+            Ok(Self { conn, owner })
+        }
+        .boxed()
+    }
+}
+
+// the sync connection of the cluster node behind ssh or a proxy
+pub struct TunnelledSyncConnection {
+    conn: redis::Connection,
+    owner: String,
+}
+
+impl redis::ConnectionLike for TunnelledSyncConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        let reply = self.conn.req_packed_command(cmd);
+        scope_reply(&self.owner, is_slots(cmd), reply)
+    }
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let replies = self.conn.req_packed_commands(cmd, offset, count);
+        scope_replies(&self.owner, replies)
+    }
+    fn get_db(&self) -> i64 {
+        self.conn.get_db()
+    }
+    fn check_connection(&mut self) -> bool {
+        self.conn.check_connection()
+    }
+    fn is_open(&self) -> bool {
+        self.conn.is_open()
+    }
+}
+
+impl redis::cluster::Connect for TunnelledSyncConnection {
+    fn connect<T>(info: T, timeout: Option<Duration>) -> RedisResult<Self>
+    where
+        T: IntoConnectionInfo,
+    {
+        let (info, owner) = tunnelled(info.into_connection_info()?)?;
+        let conn = <redis::Connection as redis::cluster::Connect>::connect(info, timeout)?;
+        Ok(Self { conn, owner })
+    }
+    fn send_packed_command(&mut self, cmd: &[u8]) -> RedisResult<()> {
+        self.conn.send_packed_command(cmd)
+    }
+    fn set_write_timeout(&self, dur: Option<Duration>) -> RedisResult<()> {
+        self.conn.set_write_timeout(dur)
+    }
+    fn set_read_timeout(&self, dur: Option<Duration>) -> RedisResult<()> {
+        self.conn.set_read_timeout(dur)
+    }
+    // the client only pipelines the routed commands, never the slots
+    fn recv_response(&mut self) -> RedisResult<Value> {
+        let reply = self.conn.recv_response();
+        scope_reply(&self.owner, false, reply)
+    }
+}
+
+// the async cluster connection, every node is tunnelled if the cluster is behind ssh or a proxy
+pub enum AsyncClusterConnection {
+    Direct(ClusterConnection),
+    Tunnelled(ClusterConnection<TunnelledConnection>),
+}
+
+impl ConnectionLike for AsyncClusterConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Direct(c) => c.req_packed_command(cmd),
+            Self::Tunnelled(c) => c.req_packed_command(cmd),
+        }
+    }
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Direct(c) => c.req_packed_commands(cmd, offset, count),
+            Self::Tunnelled(c) => c.req_packed_commands(cmd, offset, count),
+        }
+    }
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Direct(c) => c.get_db(),
+            Self::Tunnelled(c) => c.get_db(),
+        }
+    }
+}
+
+// the sync cluster connection, every node is tunnelled if the cluster is behind ssh or a proxy
+pub enum ClusterSyncConnection {
+    Direct(SyncClusterConnection),
+    Tunnelled(SyncClusterConnection<TunnelledSyncConnection>),
+}
+
+impl redis::ConnectionLike for ClusterSyncConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        match self {
+            Self::Direct(c) => c.req_packed_command(cmd),
+            Self::Tunnelled(c) => c.req_packed_command(cmd),
+        }
+    }
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        match self {
+            Self::Direct(c) => c.req_packed_commands(cmd, offset, count),
+            Self::Tunnelled(c) => c.req_packed_commands(cmd, offset, count),
+        }
+    }
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Direct(c) => c.get_db(),
+            Self::Tunnelled(c) => c.get_db(),
+        }
+    }
+    fn check_connection(&mut self) -> bool {
+        match self {
+            Self::Direct(c) => c.check_connection(),
+            Self::Tunnelled(c) => c.check_connection(),
+        }
+    }
+    fn is_open(&self) -> bool {
+        match self {
+            Self::Direct(c) => c.is_open(),
+            Self::Tunnelled(c) => c.is_open(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use serde::ser::SerializeStruct;
 use std::string::FromUtf8Error;
 
 
@@ -10,8 +11,6 @@ pub enum CusError {
     #[error(transparent)]
     Sqlite(#[from] rusqlite::Error),
     #[error(transparent)]
-    Reqwest(#[from] reqwest::Error),
-    #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Redis(#[from] redis::RedisError),
@@ -21,6 +20,14 @@ pub enum CusError {
     Utf8(#[from] FromUtf8Error),
     #[error("{0}")]
     App(String),
+    #[error("Readonly connection, {0} is not allowed")]
+    Readonly(String),
+    // the token and the command
+    #[error("{1} needs confirmation, token: {0}")]
+    Confirm(String, String),
+    // the host, the key type and the sha256 fingerprint of the unknown ssh host
+    #[error("Unknown host {0}, {1} key fingerprint is SHA256:{2}")]
+    HostKey(String, String, String),
 }
 
 impl CusError {
@@ -36,6 +43,12 @@ impl CusError {
     pub fn key_not_exists() -> Self {
         Self::App(String::from("Key not exists"))
     }
+    pub fn locked() -> Self {
+        Self::App(String::from("Please unlock with the master password"))
+    }
+    pub fn cancelled() -> Self {
+        Self::App(String::from("Request cancelled"))
+    }
 }
 
 // we must manually implement serde::Serialize
@@ -44,8 +57,26 @@ impl serde::Serialize for CusError {
     where
         S: serde::ser::Serializer,
     {
+        // the errors the frontend acts on are objects with a code, the others are the message
         match &self {
             CusError::App(s) => serializer.serialize_str(s),
+            CusError::Confirm(token, command) => {
+                let mut s = serializer.serialize_struct("CusError", 4)?;
+                s.serialize_field("code", "confirm")?;
+                s.serialize_field("message", &self.to_string())?;
+                s.serialize_field("token", token)?;
+                s.serialize_field("command", command)?;
+                s.end()
+            }
+            CusError::HostKey(host, key_type, fingerprint) => {
+                let mut s = serializer.serialize_struct("CusError", 5)?;
+                s.serialize_field("code", "host_key")?;
+                s.serialize_field("message", &self.to_string())?;
+                s.serialize_field("host", host)?;
+                s.serialize_field("key_type", key_type)?;
+                s.serialize_field("fingerprint", fingerprint)?;
+                s.end()
+            }
             _ => serializer.serialize_str(self.to_string().as_ref()),
         }
     }
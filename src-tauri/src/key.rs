@@ -121,7 +121,7 @@ impl Key {
                 self.db,
             )
             .await?;
-        self.data = FieldValue::Str(String::from_utf8_lossy(&value).to_string());
+        self.data = FieldValue::bytes(value);
         Ok(())
     }
     pub async fn get_length<'r>(
@@ -135,6 +135,7 @@ impl Key {
             "list" => "LLEN",
             "set" => "SCARD",
             "zset" => "ZCARD",
+            "stream" => "XLEN",
             "MBbloom--" => "BF.CARD",
             _ => "",
         };
@@ -1,14 +1,20 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
+mod binary;
+mod codec;
 mod connection;
 mod err;
+mod exchange;
 mod key;
 mod model;
+mod proxy;
 mod pubsub;
 mod request;
 mod response;
 mod route;
 mod sqlite;
 mod ssh;
+mod task;
+mod tls;
 mod utils;
 use pubsub::PubsubManager;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -23,6 +29,7 @@ pub fn run() {
         .manage(PubsubManager::new())
         .manage(connection::Manager::new())
         .manage(connection::EventManager::new())
+        .manage(task::TaskManager::new())
         .invoke_handler(tauri::generate_handler![route::dispatch])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,221 @@
+use crate::{err::CusError, utils};
+use base64::Engine;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot::{self, Receiver};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProxyKind {
+    Socks5,
+    Http,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyParams {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout: Option<Duration>,
+}
+
+impl ProxyParams {
+    // the proxy endpoint shown in the connection info
+    pub fn endpoint(&self) -> String {
+        let scheme = match self.kind {
+            ProxyKind::Socks5 => "socks5",
+            ProxyKind::Http => "http",
+        };
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+
+    fn credentials(&self) -> Option<(&str, &str)> {
+        self.username
+            .as_deref()
+            .filter(|u| !u.is_empty())
+            .map(|u| (u, self.password.as_deref().unwrap_or_default()))
+    }
+}
+
+// connect to the target through the proxy
+pub async fn connect(params: &ProxyParams, host: &str, port: u16) -> Result<TcpStream, CusError> {
+    let handshake = async {
+        let mut stream = TcpStream::connect((params.host.as_str(), params.port))
+            .await
+            .map_err(|e| CusError::App(format!("Proxy Connect Fail: {}", e)))?;
+        utils::set_keepalive(&stream)?;
+        match params.kind {
+            ProxyKind::Socks5 => socks5(&mut stream, params, host, port).await?,
+            ProxyKind::Http => http_connect(&mut stream, params, host, port).await?,
+        }
+        Ok(stream)
+    };
+    match params.timeout {
+        Some(t) => match tokio::time::timeout(t, handshake).await {
+            Ok(r) => r,
+            Err(_) => Err(CusError::build("Proxy Connection Timeout")),
+        },
+        None => handshake.await,
+    }
+}
+
+// forward a local port to the target through the proxy, every accepted connection dials the proxy
+// the proxy is checked before the port is returned, the forwarder stops when the receiver is closed
+pub async fn forward(
+    params: ProxyParams,
+    host: String,
+    port: u16,
+) -> Result<(SocketAddr, Receiver<()>), CusError> {
+    let mut first = Some(connect(&params, &host, port).await?);
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let addr = listener.local_addr()?;
+    let (mut tx, rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        loop {
+            let mut local = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((local, _)) => local,
+                    Err(_) => break,
+                },
+                _ = tx.closed() => break,
+            };
+            let remote = first.take();
+            let (params, host) = (params.clone(), host.clone());
+            tokio::spawn(async move {
+                let remote = match remote {
+                    Some(r) => Ok(r),
+                    None => connect(&params, &host, port).await,
+                };
+                if let Ok(mut remote) = remote {
+                    let _ = tokio::io::copy_bidirectional(&mut local, &mut remote).await;
+                }
+            });
+        }
+    });
+    Ok((addr, rx))
+}
+
+fn proxy_err(message: &str) -> CusError {
+    CusError::App(format!("Proxy Error: {}", message))
+}
+
+// the socks5 fields are prefixed by a one byte length
+fn field_len(value: &str, name: &str) -> Result<u8, CusError> {
+    u8::try_from(value.len()).map_err(|_| proxy_err(&format!("{} is longer than 255 bytes", name)))
+}
+
+// https://www.rfc-editor.org/rfc/rfc1928
+async fn socks5(
+    stream: &mut TcpStream,
+    params: &ProxyParams,
+    host: &str,
+    port: u16,
+) -> Result<(), CusError> {
+    let credentials = params.credentials();
+    match credentials {
+        Some(_) => stream.write_all(&[0x05, 0x02, 0x00, 0x02]).await?,
+        None => stream.write_all(&[0x05, 0x01, 0x00]).await?,
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 {
+        return Err(proxy_err("not a socks5 proxy"));
+    }
+    match (reply[1], credentials) {
+        (0x00, _) => {}
+        // https://www.rfc-editor.org/rfc/rfc1929
+        (0x02, Some((username, password))) => {
+            let mut auth = vec![0x01, field_len(username, "username")?];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(field_len(password, "password")?);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(proxy_err("authentication failed"));
+            }
+        }
+        _ => return Err(proxy_err("no acceptable authentication method")),
+    }
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            request.push(0x03);
+            request.push(field_len(host, "host")?);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0x00 {
+        let message = match head[1] {
+            0x01 => "general failure",
+            0x02 => "connection not allowed by ruleset",
+            0x03 => "network unreachable",
+            0x04 => "host unreachable",
+            0x05 => "connection refused",
+            0x06 => "ttl expired",
+            0x07 => "command not supported",
+            0x08 => "address type not supported",
+            _ => "unknown error",
+        };
+        return Err(proxy_err(message));
+    }
+    // skip the bound address
+    let len = match head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_err("address type not supported")),
+    };
+    let mut bound = vec![0u8; len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    params: &ProxyParams,
+    host: &str,
+    port: u16,
+) -> Result<(), CusError> {
+    let target = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => format!("[{}]:{}", host, port),
+        _ => format!("{}:{}", host, port),
+    };
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if let Some((username, password)) = params.credentials() {
+        let token =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    // read the response head byte by byte, the tunnelled data must not be consumed
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(proxy_err("response is too large"));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        _ => Err(proxy_err(status)),
+    }
+}
@@ -48,6 +48,11 @@ impl PubsubManager {
         }
     }
 
+    // remove the item whose loop has stopped by itself
+    pub fn remove(&self, name: &String) {
+        self.0.lock().unwrap().remove(name);
+    }
+
     pub fn get_conns(&self) -> Vec<response::Conn> {
         let mut vec = vec![];
         for (_, v) in self.0.lock().unwrap().iter() {
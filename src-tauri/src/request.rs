@@ -43,8 +43,8 @@ pub struct SingleValueArgs<T = String> {
 }
 
 #[derive(Deserialize)]
-pub struct FieldValueItem<T = String> {
-    pub field: String,
+pub struct FieldValueItem<T = String, F = String> {
+    pub field: F,
     pub value: T,
 }
 
@@ -1,3 +1,4 @@
+use crate::binary::{self, Binary};
 use crate::connection::CValue;
 use crate::err::CusError;
 use chrono::prelude::*;
@@ -53,6 +54,8 @@ where
 #[derive(Clone, Debug)]
 pub enum FieldValue {
     Str(String),
+    // the bytes are not utf-8
+    Binary(Vec<u8>),
     Vec(Vec<Field>),
     DimVec(Vec<Vec<Field>>),
     Int(i64),
@@ -60,6 +63,16 @@ pub enum FieldValue {
     Nil,
 }
 
+impl FieldValue {
+    // the text if the bytes are utf-8
+    pub fn bytes(v: Vec<u8>) -> Self {
+        match String::from_utf8(v) {
+            Ok(s) => Self::Str(s),
+            Err(e) => Self::Binary(e.into_bytes()),
+        }
+    }
+}
+
 impl Default for FieldValue {
     fn default() -> Self {
         Self::Nil
@@ -73,6 +86,7 @@ impl Serialize for FieldValue {
     {
         match &self {
             FieldValue::Str(s) =>  serializer.serialize_str(s),
+            FieldValue::Binary(v) => binary::serialize(v, serializer),
             FieldValue::Vec(v) => v.serialize(serializer),
             FieldValue::Int(v) => v.serialize(serializer),
             FieldValue::DimVec(v) => v.serialize(serializer),
@@ -88,13 +102,13 @@ pub fn build_fields(value_vec: &[Value]) -> Result<Vec<Field>, CusError> {
     while i < length {
         if let Some(field) = value_vec.get(i) {
             let mut f = Field {
-                field: String::from_redis_value(field)?,
+                field: Binary::from_redis_value(field)?,
                 value: FieldValue::default(),
             };
             if let Some(value) = value_vec.get(i + 1) {
                 match value {
                     Value::BulkString(v) => {
-                        f.value = FieldValue::Str(String::from_utf8_lossy(v).to_string());
+                        f.value = FieldValue::bytes(v.clone());
                     }
                     Value::Int(v) => {
                         f.value = FieldValue::Int(*v);
@@ -152,7 +166,7 @@ pub fn build_simple_fields(value_vec: &[Value]) -> Result<Vec<Field>, CusError>
                 if let Some(value) = v.first() {
                     if let Some(field) = v.get(1) {
                         r.push(Field {
-                            field: String::from_redis_value(field)?,
+                            field: Binary::from_redis_value(field)?,
                             value: FieldValue::Int(i64::from_redis_value(value)?),
                         })
                     }
@@ -168,7 +182,7 @@ pub fn build_simple_fields(value_vec: &[Value]) -> Result<Vec<Field>, CusError>
 
 #[derive(Serialize, Debug, Default, Clone)]
 pub struct Field {
-    pub field: String,
+    pub field: Binary,
     pub value: FieldValue,
 }
 
@@ -207,6 +221,19 @@ impl ScanLikeResult<String, String> {
     }
 }
 
+impl ScanLikeResult<Binary, String> {
+    pub fn build(value: Vec<Value>) -> Result<Self, CusError> {
+        let mut result = ScanLikeResult::default();
+        if let Some(first) = value.first() {
+            result.cursor = String::from_redis_value(first)?;
+        }
+        if let Some(second) = value.get(1) {
+            result.values = Vec::from_redis_value(second)?;
+        }
+        Ok(result)
+    }
+}
+
 impl ScanLikeResult<Field, String> {
     pub fn build(value: Vec<Value>) -> Result<Self, CusError> {
         let mut result = ScanLikeResult::default();
@@ -0,0 +1,79 @@
+use crate::{
+    binary::Binary,
+    codec::{self, protobuf, Decoded, Info},
+    err::CusError,
+    request::IdArgs,
+    sqlite::{Formatter, ProtoSchema},
+};
+use prost_reflect::MessageDescriptor;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct DecodeArgs {
+    value: Binary,
+    // the key name, the remembered chain of its pattern is used
+    name: Option<String>,
+    chain: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct EncodeArgs {
+    value: String,
+    // the key name, the protobuf schema of its pattern is used
+    name: Option<String>,
+    chain: Vec<String>,
+}
+
+// the protobuf message of the schema matched by the key
+fn schema_of(name: Option<&str>) -> Result<Option<MessageDescriptor>, CusError> {
+    match name {
+        Some(name) => ProtoSchema::find(name)?
+            .map(|s| protobuf::descriptor(&s.descriptor, &s.message))
+            .transpose(),
+        None => Ok(None),
+    }
+}
+
+pub async fn list() -> Result<Vec<Info>, CusError> {
+    Ok(codec::list())
+}
+
+// the chain of the args, the formatter of the key, or the detected one
+pub async fn decode(payload: String) -> Result<Decoded, CusError> {
+    let args: DecodeArgs = serde_json::from_str(&payload)?;
+    let mut chain = args.chain;
+    if chain.is_none() {
+        if let Some(name) = &args.name {
+            chain = Formatter::find(name)?.map(|f| f.chain);
+        }
+    }
+    let schema = schema_of(args.name.as_deref())?;
+    codec::decode(&args.value.0, chain.as_deref(), schema.as_ref())
+}
+
+pub async fn encode(payload: String) -> Result<Binary, CusError> {
+    let args: EncodeArgs = serde_json::from_str(&payload)?;
+    let schema = schema_of(args.name.as_deref())?;
+    Ok(Binary(codec::encode(
+        &args.value,
+        &args.chain,
+        schema.as_ref(),
+    )?))
+}
+
+pub async fn formatters() -> Result<Vec<Formatter>, CusError> {
+    Formatter::all()
+}
+
+pub async fn save_formatter(payload: String) -> Result<Formatter, CusError> {
+    let mut formatter: Formatter = serde_json::from_str(&payload)?;
+    formatter.save()?;
+    Ok(formatter)
+}
+
+pub async fn del_formatter(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let formatter = Formatter::first(args.id)?;
+    formatter.del()?;
+    Ok(())
+}
@@ -1,10 +1,13 @@
 use crate::{
-    connection::{ConnectionWrapper, Manager},
+    connection::{self, ConnectionWrapper, Manager},
     err::CusError,
+    exchange::{self, SecretMode},
     request::IdArgs,
     sqlite::Connection,
 };
-use tauri::State;
+use serde::Deserialize;
+use std::sync::Arc;
+use tauri::{State, Window};
 
 pub fn add(payload: String) -> Result<Connection, CusError> {
     let mut connection: Connection = serde_json::from_str(&payload)?;
@@ -28,10 +31,11 @@ pub fn update(payload: String) -> Result<Connection, CusError> {
     Ok(conn)
 }
 
-pub async fn open(cid: u32, manager: State<'_, Manager>) -> Result<(), CusError> {
+pub async fn open(cid: u32, window: Window, manager: State<'_, Manager>) -> Result<(), CusError> {
     let connection = Connection::first(cid)?;
     let conn = ConnectionWrapper::build(connection).await?;
-    manager.add(cid, conn).await;
+    let conn = manager.add(cid, conn).await;
+    connection::watch(cid, Arc::downgrade(&conn), window);
     Ok(())
 }
 
@@ -39,3 +43,35 @@ pub async fn close(cid: u32, manager: State<'_, Manager>) -> Result<(), CusError
     manager.remove(cid).await;
     Ok(())
 }
+
+#[derive(Deserialize)]
+struct ExportArgs {
+    ids: Option<Vec<i64>>,
+    #[serde(default)]
+    secret: SecretMode,
+    password: Option<String>,
+}
+
+// export the saved connections as json
+pub fn export(payload: String) -> Result<String, CusError> {
+    let args: ExportArgs = serde_json::from_str(&payload)?;
+    let mut connections = Connection::all()?;
+    if let Some(ids) = args.ids {
+        connections.retain(|c| c.id.is_some_and(|id| ids.contains(&id)));
+    }
+    exchange::export(connections, args.secret, args.password)
+}
+
+#[derive(Deserialize)]
+struct ImportArgs {
+    content: String,
+    password: Option<String>,
+}
+
+// import the connections from the exported json, other redis gui or redis urls
+pub fn import(payload: String) -> Result<Vec<Connection>, CusError> {
+    let args: ImportArgs = serde_json::from_str(&payload)?;
+    let mut connections = exchange::import(&args.content, args.password)?;
+    Connection::save_all(&mut connections)?;
+    Ok(connections)
+}
@@ -0,0 +1,28 @@
+use crate::{err::CusError, request::IdArgs, sqlite::Group};
+
+pub async fn all() -> Result<Vec<Group>, CusError> {
+    Group::all()
+}
+
+pub async fn add(payload: String) -> Result<Group, CusError> {
+    let mut group: Group = serde_json::from_str(&payload)?;
+    group.id = None;
+    group.save()?;
+    Ok(group)
+}
+
+pub async fn update(payload: String) -> Result<Group, CusError> {
+    let mut group: Group = serde_json::from_str(&payload)?;
+    if group.id.is_none() {
+        return Err(CusError::build("Folder not found"));
+    }
+    group.save()?;
+    Ok(group)
+}
+
+pub async fn del(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let group = Group::first(args.id)?;
+    group.del()?;
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::binary::Binary;
 use crate::connection::{CValue, Manager};
 use crate::err::CusError;
 use crate::request::{CommonValueArgs, FieldValueArgs, FieldValueItem, ItemScanArgs, NameArgs};
@@ -27,11 +28,12 @@ pub async fn hset(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<Vec<FieldValueItem<String>>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<FieldValueItem<Binary, Binary>>> =
+        serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("HSET");
     cmd.arg(&args.name);
     for x in args.value {
-        cmd.arg(&[x.field, x.value]);
+        cmd.arg(x.field).arg(x.value);
     }
     manager.execute(cid, &mut cmd, args.db).await
 }
@@ -41,7 +43,7 @@ pub async fn hdel(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -56,7 +58,7 @@ pub async fn hexists(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<String> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -71,7 +73,7 @@ pub async fn hget(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<String> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -97,7 +99,7 @@ pub async fn hincrby(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs<i64, String> = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<i64, Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -115,7 +117,7 @@ pub async fn hincrby_float(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<String, Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -155,7 +157,7 @@ pub async fn hmget(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -186,7 +188,7 @@ pub async fn hsetnx(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<Binary, Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -204,7 +206,7 @@ pub async fn hstr_len(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -278,7 +278,7 @@ pub async fn object(
             )
             .await?;
         resp.push(Field {
-            field: String::from("OBJECT ENCODING"),
+            field: "OBJECT ENCODING".into(),
             value: FieldValue::Value(s),
         })
     }
@@ -292,7 +292,7 @@ pub async fn object(
             )
             .await?;
         resp.push(Field {
-            field: String::from("OBJECT FREQ"),
+            field: "OBJECT FREQ".into(),
             value: FieldValue::Value(s),
         })
     }
@@ -306,7 +306,7 @@ pub async fn object(
             )
             .await?;
         resp.push(Field {
-            field: String::from("OBJECT IDLETIME"),
+            field: "OBJECT IDLETIME".into(),
             value: FieldValue::Value(s),
         })
     }
@@ -320,7 +320,7 @@ pub async fn object(
             )
             .await?;
         resp.push(Field {
-            field: String::from("OBJECT REFCOUNT"),
+            field: "OBJECT REFCOUNT".into(),
             value: FieldValue::Value(s),
         })
     }
@@ -2,6 +2,7 @@ use redis::{FromRedisValue, Value};
 use serde::Deserialize;
 
 use crate::{
+    binary::Binary,
     connection::{CValue, Manager},
     err::CusError,
     request::{CommonValueArgs, FieldValueArgs, NameArgs, RangeArgs},
@@ -24,7 +25,7 @@ pub async fn bl_move(
 ) -> Result<CValue, CusError> {
     let args: MoveArgs = serde_json::from_str(&payload)?;
     let v: Value = manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BLMOVE")
                 .arg(args.source)
@@ -64,7 +65,7 @@ pub async fn blm_pop(
     if let Some(v) = args.count {
         cmd.arg(("COUNT", v));
     }
-    let v: Value = manager.execute(cid, &mut cmd, args.db).await?;
+    let v: Value = manager.execute_blocking(cid, &mut cmd, args.db).await?;
     match v {
         Value::Nil => Err(CusError::build("No element could be popped.")),
         _ => Ok(CValue::from_redis_value(&v)?),
@@ -78,7 +79,7 @@ pub async fn bl_pop(
 ) -> Result<CValue, CusError> {
     let args: CommonValueArgs<f64, Vec<String>> = serde_json::from_str(&payload)?;
     let v: Value = manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BLPOP").arg(args.name).arg(args.value),
             args.db,
@@ -99,7 +100,7 @@ pub async fn br_pop(
 ) -> Result<CValue, CusError> {
     let args: CommonValueArgs<f64, Vec<String>> = serde_json::from_str(&payload)?;
     let v: Value = manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BRPOP").arg(args.name).arg(args.value),
             args.db,
@@ -120,7 +121,7 @@ pub async fn br_pop_lpush(
 ) -> Result<CValue, CusError> {
     let args: MoveArgs<i64> = serde_json::from_str(&payload)?;
     let v: Value = manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BRPOPLPUSH")
                 .arg(args.source)
@@ -183,7 +184,7 @@ pub async fn lmove(
     payload: String,
     cid: u32,
     manager: tauri::State<'_, Manager>,
-) -> Result<String, CusError> {
+) -> Result<Binary, CusError> {
     let args: MoveArgs = serde_json::from_str(&payload)?;
     manager
         .execute(
@@ -203,8 +204,8 @@ struct InsertArgs {
     name: String,
     db: Option<u8>,
     whereto: String,
-    value: String,
-    pivot: String,
+    value: Binary,
+    pivot: Binary,
 }
 pub async fn linsert(
     payload: String,
@@ -249,7 +250,7 @@ pub async fn lpop(
 struct LPosArgs {
     name: String,
     db: Option<u8>,
-    element: String,
+    element: Binary,
     rank: Option<i64>,
     count: Option<i64>,
     len: Option<i64>,
@@ -280,7 +281,7 @@ pub async fn lpush(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -295,7 +296,7 @@ pub async fn lpush_x(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -328,7 +329,7 @@ pub async fn lrem(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs<i64> = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<i64, Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -346,7 +347,7 @@ pub async fn lset(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<String, CusError> {
-    let args: FieldValueArgs<String, i64> = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<Binary, i64> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -381,7 +382,7 @@ pub async fn rpop(
     payload: String,
     cid: u32,
     manager: tauri::State<'_, Manager>,
-) -> Result<String, CusError> {
+) -> Result<CValue, CusError> {
     let args: CommonValueArgs<Option<i64>> = serde_json::from_str(&payload)?;
     manager
         .execute(
@@ -418,7 +419,7 @@ pub async fn rpush(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     let value: i64 = manager
         .execute(
             cid,
@@ -437,7 +438,7 @@ pub async fn rpushx(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
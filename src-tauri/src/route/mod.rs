@@ -1,20 +1,24 @@
 use tauri::Window;
 
-use crate::connection::{EventManager, Manager};
+use crate::connection::{EventManager, Manager, CONFIRM_TOKEN};
 use crate::err::CusError;
 use crate::pubsub::PubsubManager;
 use crate::response::Response;
+use crate::task::TaskManager;
+use tokio::sync::oneshot;
 
 pub mod bloom;
 pub mod client;
 pub mod cluster;
 pub mod cms;
+pub mod codec;
 pub mod collection;
 pub mod config;
 pub mod connection;
 pub mod cuckoo;
 pub mod db;
 pub mod debug;
+pub mod group;
 pub mod hash;
 pub mod hyperloglog;
 pub mod json;
@@ -22,10 +26,17 @@ pub mod key;
 pub mod list;
 pub mod memory;
 pub mod migrate;
+pub mod policy;
 pub mod pubsub;
+pub mod request;
+pub mod secret;
+pub mod sentinel;
 pub mod server;
 pub mod set;
+pub mod ssh;
+pub mod stream;
 pub mod string;
+pub mod tag;
 pub mod tdigest;
 pub mod terminal;
 pub mod timeseries;
@@ -34,7 +45,45 @@ pub mod transfer;
 pub mod zset;
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn dispatch(
+    pubsub: tauri::State<'_, PubsubManager>,
+    manager: tauri::State<'_, Manager>,
+    event_manage: tauri::State<'_, EventManager>,
+    task_manager: tauri::State<'_, TaskManager>,
+    window: Window,
+    path: String,
+    cid: u32,
+    payload: String,
+    request_id: Option<String>,
+    confirm: Option<String>,
+) -> Result<String, CusError> {
+    if path == "request/cancel" {
+        return Response::string(request::cancel(payload, task_manager).await?);
+    }
+    // the confirmation token of dangerous command is read by the manager
+    let fut = CONFIRM_TOKEN.scope(
+        confirm,
+        handle(pubsub, manager, event_manage, window, path, cid, payload),
+    );
+    // request with id can be cancelled by request/cancel
+    // the handling future is dropped when cancelled
+    match request_id {
+        Some(id) => {
+            let (tx, rx) = oneshot::channel();
+            task_manager.add(id.clone(), tx);
+            let r = tokio::select! {
+                r = fut => r,
+                Ok(_) = rx => Err(CusError::cancelled()),
+            };
+            task_manager.remove(&id);
+            r
+        }
+        None => fut.await,
+    }
+}
+
+async fn handle(
     pubsub: tauri::State<'_, PubsubManager>,
     manager: tauri::State<'_, Manager>,
     event_manage: tauri::State<'_, EventManager>,
@@ -43,15 +92,20 @@ pub async fn dispatch(
     cid: u32,
     payload: String,
 ) -> Result<String, CusError> {
-    dbg!(&path);
-    dbg!(&payload);
     let r = match path.as_str() {
         "connections/get" => Response::string(connection::get().await?),
         "connections/add" => Response::string(connection::add(payload)?),
         "connections/del" => Response::string(connection::del(payload)?),
         "connections/update" => Response::string(connection::update(payload)?),
-        "connections/open" => Response::string(connection::open(cid, manager).await?),
+        "connections/export" => Response::string(connection::export(payload)?),
+        "connections/import" => Response::string(connection::import(payload)?),
+        "connections/open" => Response::string(connection::open(cid, window, manager).await?),
         "connections/close" => Response::string(connection::close(cid, manager).await?),
+        "secret/status" => Response::string(secret::status().await?),
+        "secret/setup" => Response::string(secret::setup(payload).await?),
+        "secret/unlock" => Response::string(secret::unlock(payload).await?),
+        "secret/lock" => Response::string(secret::lock().await?),
+        "secret/change" => Response::string(secret::change(payload).await?),
         "server/ping" => Response::string(server::ping(payload, manager).await?),
         "server/info" => Response::string(server::info(cid, manager).await?),
         "server/version" => Response::string(server::version(cid, manager).await?),
@@ -181,6 +235,34 @@ pub async fn dispatch(
         "string/getset" => Response::string(string::getset(payload, cid, manager).await?),
         "string/mset" => Response::string(string::mset(payload, cid, manager).await?),
 
+        "stream/range" => Response::string(stream::range(payload, cid, manager).await?),
+        "stream/xlen" => Response::string(stream::xlen(payload, cid, manager).await?),
+        "stream/xadd" => Response::string(stream::xadd(payload, cid, manager).await?),
+        "stream/xdel" => Response::string(stream::xdel(payload, cid, manager).await?),
+        "stream/xtrim" => Response::string(stream::xtrim(payload, cid, manager).await?),
+        "stream/info" => Response::string(stream::info(payload, cid, manager).await?),
+        "stream/tail" => Response::string(stream::tail(window, pubsub, payload, cid).await?),
+        "stream/groups" => Response::string(stream::groups(payload, cid, manager).await?),
+        "stream/consumers" => Response::string(stream::consumers(payload, cid, manager).await?),
+        "stream/pending-summary" => {
+            Response::string(stream::pending_summary(payload, cid, manager).await?)
+        }
+        "stream/pending" => Response::string(stream::pending(payload, cid, manager).await?),
+        "stream/xclaim" => Response::string(stream::claim(payload, cid, manager).await?),
+        "stream/xautoclaim" => Response::string(stream::auto_claim(payload, cid, manager).await?),
+        "stream/xack" => Response::string(stream::ack(payload, cid, manager).await?),
+        "stream/group-create" => Response::string(stream::group_create(payload, cid, manager).await?),
+        "stream/group-setid" => Response::string(stream::group_set_id(payload, cid, manager).await?),
+        "stream/group-destroy" => {
+            Response::string(stream::group_destroy(payload, cid, manager).await?)
+        }
+        "stream/group-createconsumer" => {
+            Response::string(stream::group_create_consumer(payload, cid, manager).await?)
+        }
+        "stream/group-delconsumer" => {
+            Response::string(stream::group_del_consumer(payload, cid, manager).await?)
+        }
+
         "memory/analysis" => Response::string(memory::analysis(payload, cid, manager).await?),
         "memory/usage" => Response::string(memory::memory_usage(payload, cid, manager).await?),
         "memory/doctor" => Response::string(memory::memory_doctor(cid, manager).await?),
@@ -204,10 +286,20 @@ pub async fn dispatch(
         "cluster/nodes" => Response::string(cluster::node(cid, manager).await?),
         "cluster/nodesize" => Response::string(cluster::node_size(cid, payload, manager).await?),
         "cluster/analysis" => Response::string(cluster::analysis(cid, payload, manager).await?),
+        "sentinel/masters" => Response::string(sentinel::masters(cid, manager).await?),
+        "sentinel/master" => Response::string(sentinel::master(cid, manager).await?),
+        "sentinel/replicas" => Response::string(sentinel::replicas(cid, manager).await?),
+        "sentinel/sentinels" => Response::string(sentinel::sentinels(cid, manager).await?),
         "debug/log" => Response::string(debug::log(manager, window).await?),
         "debug/cancel" => Response::string(debug::cancel(manager).await?),
         "debug/clients" => Response::string(debug::clients(manager, pubsub).await?),
         "transfer/php_unserialize" => Response::string(transfer::php_unserialize(payload).await?),
+        "transfer/php_serialize" => Response::string(transfer::php_serialize(payload).await?),
+        "transfer/protobuf" => Response::string(transfer::protobuf(payload).await?),
+        "transfer/protobuf/encode" => Response::string(transfer::protobuf_encode(payload).await?),
+        "transfer/protobuf/schemas" => Response::string(transfer::protobuf_schemas().await?),
+        "transfer/protobuf/schemas/save" => Response::string(transfer::save_protobuf_schema(payload).await?),
+        "transfer/protobuf/schemas/del" => Response::string(transfer::del_protobuf_schema(payload).await?),
         "json/set" => Response::string(json::set(payload, cid, manager).await?),
 
         "topk/list" => Response::string(topk::list(payload, cid, manager).await?),
@@ -280,6 +372,30 @@ pub async fn dispatch(
         "collections/add" => Response::string(collection::add(payload).await?),
         "collections/del" => Response::string(collection::del(payload).await?),
 
+        "policies" => Response::string(policy::all().await?),
+        "policies/add" => Response::string(policy::add(payload).await?),
+        "policies/del" => Response::string(policy::del(payload).await?),
+
+        "groups" => Response::string(group::all().await?),
+        "groups/add" => Response::string(group::add(payload).await?),
+        "groups/update" => Response::string(group::update(payload).await?),
+        "groups/del" => Response::string(group::del(payload).await?),
+
+        "tags/rules" => Response::string(tag::rules().await?),
+        "tags/rules/add" => Response::string(tag::add_rule(payload).await?),
+        "tags/rules/del" => Response::string(tag::del_rule(payload).await?),
+
+        "ssh/known-hosts" => Response::string(ssh::known_hosts().await?),
+        "ssh/trust" => Response::string(ssh::trust(payload).await?),
+        "ssh/forget" => Response::string(ssh::forget(payload).await?),
+
+        "codec/list" => Response::string(codec::list().await?),
+        "codec/decode" => Response::string(codec::decode(payload).await?),
+        "codec/encode" => Response::string(codec::encode(payload).await?),
+        "codec/formatters" => Response::string(codec::formatters().await?),
+        "codec/formatters/save" => Response::string(codec::save_formatter(payload).await?),
+        "codec/formatters/del" => Response::string(codec::del_formatter(payload).await?),
+
         _ => Err(CusError::App(format!("{} Not Found", path))),
     };
     r
@@ -0,0 +1,18 @@
+use crate::{err::CusError, request::IdArgs, sqlite::Policy};
+
+pub async fn all() -> Result<Vec<Policy>, CusError> {
+    Policy::all()
+}
+
+pub async fn add(payload: String) -> Result<Policy, CusError> {
+    let mut policy: Policy = serde_json::from_str(&payload)?;
+    policy.save()?;
+    Ok(policy)
+}
+
+pub async fn del(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let policy = Policy::first(args.id)?;
+    policy.del()?;
+    Ok(())
+}
@@ -37,7 +37,10 @@ pub async fn subscribe(
     let model = ConnectionModel::first(cid)?;
     let (tx, rx) = oneshot::channel::<()>();
     // a channel to stop loop when frontend close the page
-    let connection = Connection::new(model.get_params());
+    let mut connection = Connection::new(model.get_params());
+    connection.resolve_master().await?;
+    // the tunnel is closed when the connection is dropped after the loop
+    connection.open_tunnel().await?;
     pubsub_manager.add(
         event_name.clone(),
         PubsubItem::new(
@@ -56,6 +59,8 @@ pub async fn subscribe(
             }
             Ok(mut conn) => {
                 let mut subpub = conn.as_pubsub();
+                // wait for the messages without the response timeout
+                let _ = subpub.set_read_timeout(None);
                 for x in args.channels {
                     let result = subpub.subscribe(&x);
                     match result {
@@ -126,7 +131,9 @@ pub async fn monitor(
     cid: u32,
 ) -> Result<String, CusError> {
     let model = ConnectionModel::first(cid)?;
-    let connection = Connection::new(model.get_params());
+    let mut connection = Connection::new(model.get_params());
+    connection.resolve_master().await?;
+    connection.open_tunnel().await?;
 
     let event_name = utils::random_str(32);
     let event_name_resp = event_name.clone();
@@ -168,6 +175,7 @@ pub async fn monitor(
             _ = rx => {
             }
         }
+        drop(connection);
     });
     Ok(event_name_resp)
 }
@@ -0,0 +1,14 @@
+use crate::err::CusError;
+use crate::request::IdArgs;
+use crate::task::TaskManager;
+use tauri::State;
+
+// cancel the in-flight request
+// the future is dropped, so the dedicated connection of blocking command is closed too
+pub async fn cancel(
+    payload: String,
+    task_manager: State<'_, TaskManager>,
+) -> Result<bool, CusError> {
+    let args: IdArgs<String> = serde_json::from_str(&payload)?;
+    Ok(task_manager.cancel(&args.id))
+}
@@ -0,0 +1,53 @@
+use crate::{err::CusError, sqlite::secret};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct Status {
+    enabled: bool,
+    unlocked: bool,
+}
+
+pub async fn status() -> Result<Status, CusError> {
+    Ok(Status {
+        enabled: secret::is_enabled()?,
+        unlocked: secret::is_unlocked(),
+    })
+}
+
+#[derive(Deserialize)]
+struct SecretArgs {
+    password: Option<String>,
+    key_file: Option<String>,
+}
+
+// set the master password, the saved secrets are encrypted
+pub async fn setup(payload: String) -> Result<(), CusError> {
+    let args: SecretArgs = serde_json::from_str(&payload)?;
+    secret::setup(&secret::read_secret(args.password, args.key_file)?)
+}
+
+pub async fn unlock(payload: String) -> Result<(), CusError> {
+    let args: SecretArgs = serde_json::from_str(&payload)?;
+    secret::unlock(&secret::read_secret(args.password, args.key_file)?)
+}
+
+pub async fn lock() -> Result<(), CusError> {
+    secret::lock();
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct ChangeArgs {
+    old_password: Option<String>,
+    old_key_file: Option<String>,
+    password: Option<String>,
+    key_file: Option<String>,
+}
+
+// change the master password, the saved secrets are re-encrypted
+pub async fn change(payload: String) -> Result<(), CusError> {
+    let args: ChangeArgs = serde_json::from_str(&payload)?;
+    let old = secret::read_secret(args.old_password, args.old_key_file)?;
+    let new = secret::read_secret(args.password, args.key_file)?;
+    secret::change(&old, &new)
+}
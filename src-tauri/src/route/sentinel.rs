@@ -0,0 +1,57 @@
+use crate::{
+    connection::Manager,
+    err::CusError,
+    response::{self, Field},
+};
+use redis::{FromRedisValue, Value};
+
+// the master/replica/sentinel list is replied as an array of field-value arrays
+fn build_list(value: Vec<Value>) -> Result<Vec<Vec<Field>>, CusError> {
+    let mut r = vec![];
+    for v in value {
+        let vv: Vec<Value> = Vec::from_redis_value(&v)?;
+        r.push(response::build_fields(&vv)?);
+    }
+    Ok(r)
+}
+
+pub async fn masters(
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<Vec<Field>>, CusError> {
+    let value: Vec<Value> = manager
+        .execute_sentinel(cid, redis::cmd("SENTINEL").arg("MASTERS"))
+        .await?;
+    build_list(value)
+}
+
+pub async fn master(cid: u32, manager: tauri::State<'_, Manager>) -> Result<Vec<Field>, CusError> {
+    let name = manager.get_sentinel_master_name(cid).await?;
+    let value: Vec<Value> = manager
+        .execute_sentinel(cid, redis::cmd("SENTINEL").arg("MASTER").arg(name))
+        .await?;
+    response::build_fields(&value)
+}
+
+pub async fn replicas(
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<Vec<Field>>, CusError> {
+    let name = manager.get_sentinel_master_name(cid).await?;
+    // REPLICAS is only available since redis 5.0, SLAVES works on every version
+    let value: Vec<Value> = manager
+        .execute_sentinel(cid, redis::cmd("SENTINEL").arg("SLAVES").arg(name))
+        .await?;
+    build_list(value)
+}
+
+pub async fn sentinels(
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<Vec<Field>>, CusError> {
+    let name = manager.get_sentinel_master_name(cid).await?;
+    let value: Vec<Value> = manager
+        .execute_sentinel(cid, redis::cmd("SENTINEL").arg("SENTINELS").arg(name))
+        .await?;
+    build_list(value)
+}
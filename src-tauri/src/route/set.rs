@@ -1,6 +1,7 @@
 use redis::{cmd, Value};
 use serde::Deserialize;
 
+use crate::binary::Binary;
 use crate::connection::CValue;
 use crate::err::CusError;
 use crate::request::{CommonValueArgs, FieldValueArgs, ItemScanArgs, NameArgs, SingleValueArgs};
@@ -10,7 +11,7 @@ pub async fn sscan(
     payload: String,
     cid: u32,
     manager: tauri::State<'_, Manager>,
-) -> Result<ScanLikeResult<String, String>, CusError> {
+) -> Result<ScanLikeResult<Binary, String>, CusError> {
     let args: ItemScanArgs = serde_json::from_str(&payload)?;
     let mut cmd = cmd("sscan");
     cmd.arg(&args.name)
@@ -20,7 +21,7 @@ pub async fn sscan(
         cmd.arg(&["MATCH", &format!("*{}*", search)]);
     }
     let values: Vec<Value> = manager.execute(cid, &mut cmd, args.db).await?;
-    ScanLikeResult::<String, String>::build(values)
+    ScanLikeResult::<Binary, String>::build(values)
 }
 
 pub async fn sadd(
@@ -28,7 +29,7 @@ pub async fn sadd(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     let value: i64 = manager
         .execute(
             cid,
@@ -47,7 +48,7 @@ pub async fn srem(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(payload.as_str())?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(payload.as_str())?;
     let value: i64 = manager
         .execute(
             cid,
@@ -140,7 +141,7 @@ pub async fn sis_member(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<String> = serde_json::from_str(payload.as_str())?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(payload.as_str())?;
     manager
         .execute(
             cid,
@@ -155,7 +156,7 @@ pub async fn sm_is_member(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(payload.as_str())?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(payload.as_str())?;
     manager
         .execute(
             cid,
@@ -181,7 +182,7 @@ pub async fn smove(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: FieldValueArgs = serde_json::from_str(payload.as_str())?;
+    let args: FieldValueArgs<Binary> = serde_json::from_str(payload.as_str())?;
     let v: i64 = manager
         .execute(
             cid,
@@ -0,0 +1,19 @@
+use crate::{err::CusError, request::IdArgs, sqlite::KnownHost};
+
+pub async fn known_hosts() -> Result<Vec<KnownHost>, CusError> {
+    KnownHost::all()
+}
+
+// trust the host key after the user confirmed the fingerprint
+pub async fn trust(payload: String) -> Result<KnownHost, CusError> {
+    let mut host: KnownHost = serde_json::from_str(&payload)?;
+    host.save()?;
+    Ok(host)
+}
+
+pub async fn forget(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let host = KnownHost::first(args.id)?;
+    host.del()?;
+    Ok(())
+}
@@ -0,0 +1,880 @@
+use crate::binary::Binary;
+use crate::connection::{Connectable, ConnectionWrapper, Manager};
+use crate::err::CusError;
+use crate::pubsub::{PubsubItem, PubsubManager};
+use crate::request::{CommonValueArgs, FieldValueItem, NameArgs};
+use crate::response::{self, EventResp, Field, FieldValue, ScanLikeResult};
+use crate::sqlite::Connection as ConnectionModel;
+use crate::utils;
+use redis::{FromRedisValue, Value};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager as _, State};
+use tokio::sync::oneshot;
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct StreamEntry {
+    pub id: String,
+    pub fields: Vec<Field>,
+}
+
+impl StreamEntry {
+    // an entry is replied as [id, [field, value, ...]]
+    pub fn build(v: &Value) -> Result<Self, CusError> {
+        let arr: Vec<Value> = Vec::from_redis_value(v)?;
+        let mut entry = StreamEntry::default();
+        if let Some(id) = arr.first() {
+            entry.id = String::from_redis_value(id)?;
+        }
+        if let Some(Value::Array(fields)) = arr.get(1) {
+            entry.fields = response::build_fields(fields)?;
+        }
+        Ok(entry)
+    }
+
+    pub fn build_vec(values: &[Value]) -> Result<Vec<Self>, CusError> {
+        let mut r = vec![];
+        for v in values {
+            r.push(Self::build(v)?);
+        }
+        Ok(r)
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct StreamInfo {
+    pub length: i64,
+    pub radix_tree_keys: i64,
+    pub radix_tree_nodes: i64,
+    pub groups: i64,
+    pub last_generated_id: String,
+    pub max_deleted_entry_id: Option<String>,
+    pub entries_added: Option<i64>,
+    pub recorded_first_entry_id: Option<String>,
+    pub first_entry: Option<StreamEntry>,
+    pub last_entry: Option<StreamEntry>,
+}
+
+// the next id after the given one, so the range can continue without
+// the exclusive "(" syntax which requires redis 6.2
+fn next_id(id: &str) -> Option<String> {
+    let (ms, seq) = id.split_once("-")?;
+    let ms: u64 = ms.parse().ok()?;
+    let seq: u64 = seq.parse().ok()?;
+    if seq == u64::MAX {
+        return Some(format!("{}-0", ms.checked_add(1)?));
+    }
+    Some(format!("{}-{}", ms, seq + 1))
+}
+
+// the previous id before the given one, used by the reverse range
+fn prev_id(id: &str) -> Option<String> {
+    let (ms, seq) = id.split_once("-")?;
+    let ms: u64 = ms.parse().ok()?;
+    let seq: u64 = seq.parse().ok()?;
+    if seq == 0 {
+        return Some(format!("{}-{}", ms.checked_sub(1)?, u64::MAX));
+    }
+    Some(format!("{}-{}", ms, seq - 1))
+}
+
+#[derive(Deserialize)]
+struct RangeArgs {
+    name: String,
+    db: Option<u8>,
+    cursor: Option<String>,
+    end: Option<String>,
+    count: i64,
+    rev: Option<bool>,
+}
+
+// page through the stream with XRANGE/XREVRANGE
+// the cursor is the id to start from, an empty cursor means no more entries
+pub async fn range(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<ScanLikeResult<StreamEntry, String>, CusError> {
+    let args: RangeArgs = serde_json::from_str(&payload)?;
+    let rev = args.rev.unwrap_or_default();
+    let mut cmd;
+    if rev {
+        cmd = redis::cmd("XREVRANGE");
+        cmd.arg(&args.name)
+            .arg(args.cursor.unwrap_or(String::from("+")))
+            .arg(args.end.unwrap_or(String::from("-")));
+    } else {
+        cmd = redis::cmd("XRANGE");
+        cmd.arg(&args.name)
+            .arg(args.cursor.unwrap_or(String::from("-")))
+            .arg(args.end.unwrap_or(String::from("+")));
+    }
+    cmd.arg(("COUNT", args.count));
+    let values: Vec<Value> = manager.execute(cid, &mut cmd, args.db).await?;
+    let mut result = ScanLikeResult::<StreamEntry, String> {
+        cursor: String::default(),
+        values: StreamEntry::build_vec(&values)?,
+    };
+    if result.values.len() as i64 >= args.count {
+        if let Some(last) = result.values.last() {
+            let next = if rev {
+                prev_id(&last.id)
+            } else {
+                next_id(&last.id)
+            };
+            result.cursor = next.unwrap_or_default();
+        }
+    }
+    Ok(result)
+}
+
+pub async fn xlen(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: NameArgs = serde_json::from_str(&payload)?;
+    manager
+        .execute(cid, redis::cmd("XLEN").arg(args.name), args.db)
+        .await
+}
+
+#[derive(Deserialize)]
+struct TrimArgs {
+    strategy: String,
+    threshold: String,
+    approximate: Option<bool>,
+    limit: Option<i64>,
+}
+
+impl TrimArgs {
+    fn write(&self, cmd: &mut redis::Cmd) -> Result<(), CusError> {
+        let strategy = self.strategy.to_uppercase();
+        if strategy != "MAXLEN" && strategy != "MINID" {
+            return Err(CusError::App(format!(
+                "{} is not a valid trim strategy",
+                self.strategy
+            )));
+        }
+        cmd.arg(strategy);
+        if self.approximate.unwrap_or_default() {
+            cmd.arg("~");
+        }
+        cmd.arg(&self.threshold);
+        if let Some(v) = self.limit {
+            cmd.arg(("LIMIT", v));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct AddArgs {
+    name: String,
+    db: Option<u8>,
+    id: Option<String>,
+    value: Vec<FieldValueItem>,
+    nomkstream: Option<bool>,
+    trim: Option<TrimArgs>,
+}
+
+pub async fn xadd(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<String, CusError> {
+    let args: AddArgs = serde_json::from_str(&payload)?;
+    if args.value.is_empty() {
+        return Err(CusError::build("At least one field is required"));
+    }
+    let mut cmd = redis::cmd("XADD");
+    cmd.arg(&args.name);
+    if args.nomkstream.unwrap_or_default() {
+        cmd.arg("NOMKSTREAM");
+    }
+    if let Some(trim) = &args.trim {
+        trim.write(&mut cmd)?;
+    }
+    cmd.arg(args.id.unwrap_or(String::from("*")));
+    for x in args.value {
+        cmd.arg((x.field, x.value));
+    }
+    let v: Value = manager.execute(cid, &mut cmd, args.db).await?;
+    match v {
+        Value::Nil => Err(CusError::key_not_exists()),
+        _ => Ok(String::from_redis_value(&v)?),
+    }
+}
+
+pub async fn xdel(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    manager
+        .execute(
+            cid,
+            redis::cmd("XDEL").arg(args.name).arg(args.value),
+            args.db,
+        )
+        .await
+}
+
+pub async fn xtrim(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: CommonValueArgs<TrimArgs> = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XTRIM");
+    cmd.arg(args.name);
+    args.value.write(&mut cmd)?;
+    manager.execute(cid, &mut cmd, args.db).await
+}
+
+pub async fn info(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<StreamInfo, CusError> {
+    let args: NameArgs = serde_json::from_str(&payload)?;
+    let value: Vec<Value> = manager
+        .execute(
+            cid,
+            redis::cmd("XINFO").arg("STREAM").arg(args.name),
+            args.db,
+        )
+        .await?;
+    let mut info = StreamInfo::default();
+    let mut i = 0;
+    while i + 1 < value.len() {
+        let field = String::from_redis_value(&value[i])?;
+        let v = &value[i + 1];
+        match field.as_str() {
+            "length" => info.length = i64::from_redis_value(v)?,
+            "radix-tree-keys" => info.radix_tree_keys = i64::from_redis_value(v)?,
+            "radix-tree-nodes" => info.radix_tree_nodes = i64::from_redis_value(v)?,
+            "groups" => info.groups = i64::from_redis_value(v)?,
+            "last-generated-id" => info.last_generated_id = String::from_redis_value(v)?,
+            "max-deleted-entry-id" => info.max_deleted_entry_id = Option::from_redis_value(v)?,
+            "entries-added" => info.entries_added = Option::from_redis_value(v)?,
+            "recorded-first-entry-id" => {
+                info.recorded_first_entry_id = Option::from_redis_value(v)?
+            }
+            "first-entry" => {
+                if *v != Value::Nil {
+                    info.first_entry = Some(StreamEntry::build(v)?)
+                }
+            }
+            "last-entry" => {
+                if *v != Value::Nil {
+                    info.last_entry = Some(StreamEntry::build(v)?)
+                }
+            }
+            _ => {}
+        }
+        i += 2;
+    }
+    Ok(info)
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct StreamGroup {
+    pub name: String,
+    pub consumers: i64,
+    pub pending: i64,
+    pub last_delivered_id: String,
+    pub entries_read: Option<i64>,
+    pub lag: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct StreamConsumer {
+    pub name: String,
+    pub pending: i64,
+    pub idle: i64,
+    pub inactive: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct PendingSummary {
+    pub count: i64,
+    pub min_id: Option<String>,
+    pub max_id: Option<String>,
+    pub consumers: Vec<Field>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct PendingEntry {
+    pub id: String,
+    pub consumer: String,
+    pub idle: i64,
+    pub delivered: i64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct AutoClaimResult {
+    pub cursor: String,
+    pub values: Vec<StreamEntry>,
+    pub deleted: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GroupArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+}
+
+pub async fn groups(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<StreamGroup>, CusError> {
+    let args: NameArgs = serde_json::from_str(&payload)?;
+    let value: Vec<Value> = manager
+        .execute(
+            cid,
+            redis::cmd("XINFO").arg("GROUPS").arg(args.name),
+            args.db,
+        )
+        .await?;
+    let mut r = vec![];
+    for item in value {
+        let item: Vec<Value> = Vec::from_redis_value(&item)?;
+        let mut group = StreamGroup::default();
+        let mut i = 0;
+        while i + 1 < item.len() {
+            let field = String::from_redis_value(&item[i])?;
+            let v = &item[i + 1];
+            match field.as_str() {
+                "name" => group.name = String::from_redis_value(v)?,
+                "consumers" => group.consumers = i64::from_redis_value(v)?,
+                "pending" => group.pending = i64::from_redis_value(v)?,
+                "last-delivered-id" => group.last_delivered_id = String::from_redis_value(v)?,
+                "entries-read" => group.entries_read = Option::from_redis_value(v)?,
+                "lag" => group.lag = Option::from_redis_value(v)?,
+                _ => {}
+            }
+            i += 2;
+        }
+        r.push(group);
+    }
+    Ok(r)
+}
+
+pub async fn consumers(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<StreamConsumer>, CusError> {
+    let args: GroupArgs = serde_json::from_str(&payload)?;
+    let value: Vec<Value> = manager
+        .execute(
+            cid,
+            redis::cmd("XINFO")
+                .arg("CONSUMERS")
+                .arg(args.name)
+                .arg(args.group),
+            args.db,
+        )
+        .await?;
+    let mut r = vec![];
+    for item in value {
+        let item: Vec<Value> = Vec::from_redis_value(&item)?;
+        let mut consumer = StreamConsumer::default();
+        let mut i = 0;
+        while i + 1 < item.len() {
+            let field = String::from_redis_value(&item[i])?;
+            let v = &item[i + 1];
+            match field.as_str() {
+                "name" => consumer.name = String::from_redis_value(v)?,
+                "pending" => consumer.pending = i64::from_redis_value(v)?,
+                "idle" => consumer.idle = i64::from_redis_value(v)?,
+                "inactive" => consumer.inactive = Option::from_redis_value(v)?,
+                _ => {}
+            }
+            i += 2;
+        }
+        r.push(consumer);
+    }
+    Ok(r)
+}
+
+pub async fn pending_summary(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<PendingSummary, CusError> {
+    let args: GroupArgs = serde_json::from_str(&payload)?;
+    let value: Vec<Value> = manager
+        .execute(
+            cid,
+            redis::cmd("XPENDING").arg(args.name).arg(args.group),
+            args.db,
+        )
+        .await?;
+    let mut summary = PendingSummary::default();
+    if let Some(v) = value.first() {
+        summary.count = i64::from_redis_value(v)?;
+    }
+    if let Some(v) = value.get(1) {
+        summary.min_id = Option::from_redis_value(v)?;
+    }
+    if let Some(v) = value.get(2) {
+        summary.max_id = Option::from_redis_value(v)?;
+    }
+    if let Some(Value::Array(consumers)) = value.get(3) {
+        for c in consumers {
+            let c: Vec<Value> = Vec::from_redis_value(c)?;
+            if let (Some(name), Some(count)) = (c.first(), c.get(1)) {
+                summary.consumers.push(Field {
+                    field: Binary::from_redis_value(name)?,
+                    value: FieldValue::Int(i64::from_redis_value(count)?),
+                });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[derive(Deserialize)]
+struct PendingArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    idle: Option<i64>,
+    start: Option<String>,
+    end: Option<String>,
+    count: i64,
+    consumer: Option<String>,
+}
+
+pub async fn pending(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<PendingEntry>, CusError> {
+    let args: PendingArgs = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XPENDING");
+    cmd.arg(args.name).arg(args.group);
+    if let Some(v) = args.idle {
+        cmd.arg(("IDLE", v));
+    }
+    cmd.arg(args.start.unwrap_or(String::from("-")))
+        .arg(args.end.unwrap_or(String::from("+")))
+        .arg(args.count)
+        .arg(args.consumer);
+    let value: Vec<Value> = manager.execute(cid, &mut cmd, args.db).await?;
+    let mut r = vec![];
+    for item in value {
+        let item: Vec<Value> = Vec::from_redis_value(&item)?;
+        let mut entry = PendingEntry::default();
+        if let Some(v) = item.first() {
+            entry.id = String::from_redis_value(v)?;
+        }
+        if let Some(v) = item.get(1) {
+            entry.consumer = String::from_redis_value(v)?;
+        }
+        if let Some(v) = item.get(2) {
+            entry.idle = i64::from_redis_value(v)?;
+        }
+        if let Some(v) = item.get(3) {
+            entry.delivered = i64::from_redis_value(v)?;
+        }
+        r.push(entry);
+    }
+    Ok(r)
+}
+
+#[derive(Deserialize)]
+struct ClaimArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    consumer: String,
+    min_idle: i64,
+    ids: Vec<String>,
+    idle: Option<i64>,
+    retry_count: Option<i64>,
+    force: Option<bool>,
+}
+
+// XCLAIM, entries deleted in the meantime are replied as nil and skipped
+pub async fn claim(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<Vec<StreamEntry>, CusError> {
+    let args: ClaimArgs = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XCLAIM");
+    cmd.arg(args.name)
+        .arg(args.group)
+        .arg(args.consumer)
+        .arg(args.min_idle)
+        .arg(args.ids);
+    if let Some(v) = args.idle {
+        cmd.arg(("IDLE", v));
+    }
+    if let Some(v) = args.retry_count {
+        cmd.arg(("RETRYCOUNT", v));
+    }
+    if args.force.unwrap_or_default() {
+        cmd.arg("FORCE");
+    }
+    let value: Vec<Value> = manager.execute(cid, &mut cmd, args.db).await?;
+    let mut r = vec![];
+    for v in value {
+        if v != Value::Nil {
+            r.push(StreamEntry::build(&v)?);
+        }
+    }
+    Ok(r)
+}
+
+#[derive(Deserialize)]
+struct AutoClaimArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    consumer: String,
+    min_idle: i64,
+    cursor: Option<String>,
+    count: Option<i64>,
+}
+
+pub async fn auto_claim(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<AutoClaimResult, CusError> {
+    let args: AutoClaimArgs = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XAUTOCLAIM");
+    cmd.arg(args.name)
+        .arg(args.group)
+        .arg(args.consumer)
+        .arg(args.min_idle)
+        .arg(args.cursor.unwrap_or(String::from("0-0")));
+    if let Some(v) = args.count {
+        cmd.arg(("COUNT", v));
+    }
+    let value: Vec<Value> = manager.execute(cid, &mut cmd, args.db).await?;
+    let mut result = AutoClaimResult::default();
+    if let Some(v) = value.first() {
+        result.cursor = String::from_redis_value(v)?;
+    }
+    if let Some(Value::Array(entries)) = value.get(1) {
+        for v in entries {
+            if *v != Value::Nil {
+                result.values.push(StreamEntry::build(v)?);
+            }
+        }
+    }
+    // the deleted ids are only replied since redis 7.0
+    if let Some(v) = value.get(2) {
+        result.deleted = Vec::from_redis_value(v)?;
+    }
+    Ok(result)
+}
+
+#[derive(Deserialize)]
+struct AckArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    ids: Vec<String>,
+}
+
+pub async fn ack(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: AckArgs = serde_json::from_str(&payload)?;
+    manager
+        .execute(
+            cid,
+            redis::cmd("XACK")
+                .arg(args.name)
+                .arg(args.group)
+                .arg(args.ids),
+            args.db,
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+struct GroupCreateArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    id: Option<String>,
+    mkstream: Option<bool>,
+    entries_read: Option<i64>,
+}
+
+pub async fn group_create(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<String, CusError> {
+    let args: GroupCreateArgs = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XGROUP");
+    cmd.arg("CREATE")
+        .arg(args.name)
+        .arg(args.group)
+        .arg(args.id.unwrap_or(String::from("$")));
+    if args.mkstream.unwrap_or_default() {
+        cmd.arg("MKSTREAM");
+    }
+    if let Some(v) = args.entries_read {
+        cmd.arg(("ENTRIESREAD", v));
+    }
+    manager.execute(cid, &mut cmd, args.db).await
+}
+
+pub async fn group_set_id(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<String, CusError> {
+    let args: GroupCreateArgs = serde_json::from_str(&payload)?;
+    let mut cmd = redis::cmd("XGROUP");
+    cmd.arg("SETID")
+        .arg(args.name)
+        .arg(args.group)
+        .arg(args.id.unwrap_or(String::from("$")));
+    if let Some(v) = args.entries_read {
+        cmd.arg(("ENTRIESREAD", v));
+    }
+    manager.execute(cid, &mut cmd, args.db).await
+}
+
+pub async fn group_destroy(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: GroupArgs = serde_json::from_str(&payload)?;
+    manager
+        .execute(
+            cid,
+            redis::cmd("XGROUP")
+                .arg("DESTROY")
+                .arg(args.name)
+                .arg(args.group),
+            args.db,
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+struct ConsumerArgs {
+    name: String,
+    db: Option<u8>,
+    group: String,
+    consumer: String,
+}
+
+pub async fn group_create_consumer(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: ConsumerArgs = serde_json::from_str(&payload)?;
+    manager
+        .execute(
+            cid,
+            redis::cmd("XGROUP")
+                .arg("CREATECONSUMER")
+                .arg(args.name)
+                .arg(args.group)
+                .arg(args.consumer),
+            args.db,
+        )
+        .await
+}
+
+// returns the number of pending messages the consumer had
+pub async fn group_del_consumer(
+    payload: String,
+    cid: u32,
+    manager: tauri::State<'_, Manager>,
+) -> Result<i64, CusError> {
+    let args: ConsumerArgs = serde_json::from_str(&payload)?;
+    manager
+        .execute(
+            cid,
+            redis::cmd("XGROUP")
+                .arg("DELCONSUMER")
+                .arg(args.name)
+                .arg(args.group)
+                .arg(args.consumer),
+            args.db,
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+struct TailArgs {
+    name: String,
+    db: Option<u8>,
+    id: Option<String>,
+    group: Option<String>,
+    consumer: Option<String>,
+    noack: Option<bool>,
+    count: Option<i64>,
+    block: Option<i64>,
+}
+
+impl TailArgs {
+    // XREAD or XREADGROUP when a group is given, with the last seen id
+    fn build_cmd(&self, last_id: &str) -> redis::Cmd {
+        let mut cmd;
+        if let Some(group) = &self.group {
+            cmd = redis::cmd("XREADGROUP");
+            cmd.arg((
+                "GROUP",
+                group,
+                self.consumer.as_deref().unwrap_or("tauri-redis"),
+            ));
+        } else {
+            cmd = redis::cmd("XREAD");
+        }
+        cmd.arg(("COUNT", self.count.unwrap_or(100)))
+            .arg(("BLOCK", self.block.unwrap_or(5000)));
+        if self.group.is_some() && self.noack.unwrap_or_default() {
+            cmd.arg("NOACK");
+        }
+        cmd.arg("STREAMS").arg(&self.name).arg(last_id);
+        cmd
+    }
+}
+
+// the reply is [[key, [entry, ...]], ...] or nil when the block timeout is reached
+// the resp2 reply is an array of [key, entries], the resp3 reply is a map of key to entries
+fn build_read_reply(v: &Value) -> Result<Vec<StreamEntry>, CusError> {
+    let mut r = vec![];
+    match v {
+        Value::Array(streams) => {
+            for stream in streams {
+                let stream: Vec<Value> = Vec::from_redis_value(stream)?;
+                if let Some(Value::Array(entries)) = stream.get(1) {
+                    r.append(&mut StreamEntry::build_vec(entries)?);
+                }
+            }
+        }
+        Value::Map(streams) => {
+            for (_, entries) in streams {
+                if let Value::Array(entries) = entries {
+                    r.append(&mut StreamEntry::build_vec(entries)?);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(r)
+}
+
+// the id of the last entry, 0-0 if the stream is empty
+// $ is resolved once, as a $ sent again would miss the entries added between two reads
+async fn last_entry_id(name: &str, conn: &mut ConnectionWrapper) -> Result<String, CusError> {
+    let value: Value = redis::cmd("XREVRANGE")
+        .arg(name)
+        .arg("+")
+        .arg("-")
+        .arg(("COUNT", 1))
+        .query_async(conn)
+        .await?;
+    let entries = match value {
+        Value::Array(entries) => StreamEntry::build_vec(&entries)?,
+        _ => vec![],
+    };
+    Ok(entries
+        .first()
+        .map(|e| e.id.clone())
+        .unwrap_or(String::from("0-0")))
+}
+
+async fn tail_loop(
+    args: &TailArgs,
+    conn: &mut ConnectionWrapper,
+    window: &tauri::Window,
+    event_name: &str,
+) -> Result<(), CusError> {
+    let mut last_id = match (&args.group, args.id.as_deref()) {
+        (Some(_), _) => String::from(">"),
+        (None, None | Some("$")) => last_entry_id(&args.name, conn).await?,
+        (None, Some(id)) => id.to_string(),
+    };
+    loop {
+        let value: Value = args.build_cmd(&last_id).query_async(conn).await?;
+        for entry in build_read_reply(&value)? {
+            if args.group.is_none() {
+                last_id = entry.id.clone();
+            }
+            let r: EventResp<StreamEntry> = EventResp::new(entry, String::from(event_name));
+            let _ = window.emit(event_name, serde_json::to_string(&r)?);
+        }
+    }
+}
+
+// tail the stream on a dedicated connection and push new entries to the window
+// the loop is registered to pubsub manager, so it can be stopped by pubsub/cancel
+pub async fn tail(
+    window: tauri::Window,
+    pubsub_manager: State<'_, PubsubManager>,
+    payload: String,
+    cid: u32,
+) -> Result<String, CusError> {
+    let args: TailArgs = serde_json::from_str(&payload)?;
+    // the read blocks until the entries come, so there is no response timeout
+    let mut params = ConnectionModel::first(cid)?.get_params();
+    params.redis_params.response_timeout = None;
+    let mut conn = ConnectionWrapper::build(params).await?;
+    if !conn.is_cluster() {
+        if let Some(db) = args.db {
+            redis::cmd("select")
+                .arg(db)
+                .query_async::<String>(&mut conn)
+                .await?;
+            conn.db = db;
+        }
+    }
+    // XREADGROUP is a write command
+    conn.check_writable(&args.build_cmd("0")).await?;
+    let event_name = utils::random_str(32);
+    let event_name_resp = event_name.clone();
+    // a channel to stop loop when frontend close the page
+    let (tx, rx) = oneshot::channel::<()>();
+    pubsub_manager.add(
+        event_name.clone(),
+        PubsubItem::new(
+            tx,
+            event_name.clone(),
+            conn.get_host(),
+            "stream".to_string(),
+            conn.model.get_proxy(),
+        ),
+    );
+    tokio::spawn(async move {
+        tokio::select! {
+            r = tail_loop(&args, &mut conn, &window, &event_name) => {
+                // the loop only stops by an error, tell the window the tail is closed
+                if let Err(e) = r {
+                    let mut resp: EventResp<String> =
+                        EventResp::new(e.to_string(), event_name.clone());
+                    resp.success = false;
+                    if let Ok(s) = serde_json::to_string(&resp) {
+                        let _ = window.emit(&event_name, s);
+                    }
+                }
+                window.state::<PubsubManager>().remove(&event_name);
+            },
+            _ = rx => {
+            }
+        }
+        drop(conn);
+    });
+    Ok(event_name_resp)
+}
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 
+use crate::binary::Binary;
 use crate::connection::{CValue, Manager};
 use crate::err::CusError;
 
@@ -12,7 +13,7 @@ pub async fn set(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<String, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -27,7 +28,7 @@ pub async fn mset(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<String, CusError> {
-    let args: SingleValueArgs<Vec<FieldValueItem>> = serde_json::from_str(&payload)?;
+    let args: SingleValueArgs<Vec<FieldValueItem<Binary>>> = serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("mset");
     for x in args.value {
         cmd.arg((&x.field, &x.value));
@@ -40,7 +41,7 @@ pub async fn append(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -121,9 +122,9 @@ pub async fn get_range(
     payload: String,
     cid: u32,
     manager: tauri::State<'_, Manager>,
-) -> Result<String, CusError> {
+) -> Result<Binary, CusError> {
     let args: RangeArgs<i64> = serde_json::from_str(&payload)?;
-    let v: Vec<u8> = manager
+    manager
         .execute(
             cid,
             redis::cmd("GETRANGE")
@@ -132,8 +133,7 @@ pub async fn get_range(
                 .arg(args.end),
             args.db,
         )
-        .await?;
-    Ok(String::from_utf8_lossy(&v).to_string())
+        .await
 }
 
 pub async fn set_range(
@@ -141,7 +141,7 @@ pub async fn set_range(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: FieldValueArgs = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -221,7 +221,7 @@ pub async fn getset(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -0,0 +1,18 @@
+use crate::{err::CusError, request::IdArgs, sqlite::TagRule};
+
+pub async fn rules() -> Result<Vec<TagRule>, CusError> {
+    TagRule::all()
+}
+
+pub async fn add_rule(payload: String) -> Result<TagRule, CusError> {
+    let mut rule: TagRule = serde_json::from_str(&payload)?;
+    rule.save()?;
+    Ok(rule)
+}
+
+pub async fn del_rule(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let rule = TagRule::first(args.id)?;
+    rule.del()?;
+    Ok(())
+}
@@ -1,10 +1,10 @@
 use tauri::{Emitter, Event, Listener, Window};
 
-use crate::connection::{CValue, EventManager, Manager};
+use crate::connection::{CValue, CommandFlags, ConfirmStore, EventManager, Manager};
 use crate::err::CusError;
 use crate::request::IdArgs;
 use crate::{response::EventResp, utils};
-use redis::{Cmd, RedisResult, Value as RedisValue};
+use redis::{Cmd, ErrorKind, RedisError, RedisResult, Value as RedisValue};
 use std::cell::RefCell;
 #[derive(serde::Serialize)]
 
@@ -13,6 +13,14 @@ pub struct OpenArgs {
     receive: String,
 }
 
+// the command sent by the terminal
+// confirm is the token of the dangerous command
+#[derive(serde::Deserialize)]
+struct TerminalCommand {
+    data: Vec<String>,
+    confirm: Option<String>,
+}
+
 pub async fn open(
     cid: u32,
     window: Window,
@@ -27,9 +35,10 @@ pub async fn open(
     let window_copy = window.clone();
     fn cmd_handle<F>(s: &str, func: F) -> EventResp<CValue>
     where
-        F: Fn(Cmd) -> RedisResult<RedisValue>,
+        F: Fn(Cmd, Option<String>) -> RedisResult<RedisValue>,
     {
-        let item: EventResp<Vec<String>> = serde_json::from_str(s).unwrap();
+        let item: TerminalCommand = serde_json::from_str(s).unwrap();
+        let confirm = item.confirm;
         let cmd_vec = item.data;
         let mut resp_item: EventResp<CValue> = EventResp::new(CValue::Nil, String::new());
         if let Some(first) = cmd_vec.first() {
@@ -43,7 +52,7 @@ pub async fn open(
                     cmd.arg(cmd_vec.get(i).unwrap());
                     i += 1;
                 }
-                let result = func(cmd);
+                let result = func(cmd, confirm);
                 match result {
                     Ok(vv) => {
                         resp_item.data = CValue::build(vv);
@@ -62,12 +71,58 @@ pub async fn open(
         }
         resp_item
     }
+    // reject the write command on the readonly connection
+    fn check_writable<C: redis::ConnectionLike>(
+        cmd: &Cmd,
+        flags: &mut CommandFlags,
+        conn: &mut C,
+    ) -> RedisResult<()> {
+        if let Some(name) = flags.missing(cmd) {
+            let value: RedisValue = CommandFlags::info_cmd(&name).query(conn)?;
+            flags.store(&name, &value);
+        }
+        if flags.is_write(cmd) {
+            let err = CusError::Readonly(CommandFlags::name(cmd));
+            return Err(RedisError::from((
+                ErrorKind::ReadOnly,
+                "Readonly connection",
+                err.to_string(),
+            )));
+        }
+        Ok(())
+    }
 
+    // the dangerous command needs a confirmation
+    fn check_confirm(
+        confirms: &ConfirmStore,
+        dangerous: &(String, Vec<String>),
+        cmd: &Cmd,
+        confirm: Option<String>,
+    ) -> RedisResult<()> {
+        let (host, commands) = dangerous;
+        if let Err(err) = confirms.check(host, cmd, commands, confirm) {
+            return Err(RedisError::from((
+                ErrorKind::ClientError,
+                "Confirmation required",
+                err.to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    let readonly = manager.get_is_readonly(cid).await;
+    let cell_flags = RefCell::new(CommandFlags::default());
+    let confirms = manager.get_confirms();
+    let dangerous = manager.get_dangerous(cid).await?;
     if manager.get_is_cluster(cid).await {
         let cell_conn = RefCell::new(manager.get_sync_cluster_conn(cid).await?);
         let event_id = window.listen(inner_send_event_name.as_str(), move |event: Event| {
-            let mut resp_item = cmd_handle(event.payload(), |cmd| {
+            let mut resp_item = cmd_handle(event.payload(), |cmd, confirm| {
+                check_confirm(&confirms, &dangerous, &cmd, confirm)?;
                 let mut conn = cell_conn.borrow_mut();
+                if readonly {
+                    check_writable(&cmd, &mut cell_flags.borrow_mut(), &mut *conn)?;
+                }
                 cmd.query(&mut conn)
             });
             resp_item.event = inner_receive_event_name.clone();
@@ -79,8 +134,12 @@ pub async fn open(
     } else {
         let cell_conn = RefCell::new(manager.get_sync_conn(cid).await?);
         let event_handle = window.listen(inner_send_event_name.as_str(), move |event| {
-            let mut resp_item = cmd_handle(event.payload(), |cmd| {
+            let mut resp_item = cmd_handle(event.payload(), |cmd, confirm| {
+                check_confirm(&confirms, &dangerous, &cmd, confirm)?;
                 let mut conn = cell_conn.borrow_mut();
+                if readonly {
+                    check_writable(&cmd, &mut cell_flags.borrow_mut(), &mut *conn)?;
+                }
                 cmd.query(&mut conn)
             });
             resp_item.event = inner_receive_event_name.clone();
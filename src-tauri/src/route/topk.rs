@@ -1,3 +1,4 @@
+use crate::binary::Binary;
 use crate::connection::{CValue, Manager};
 use crate::err::CusError;
 use crate::request::{CommonValueArgs, FieldValueItem, NameArgs};
@@ -57,7 +58,7 @@ pub async fn list(
             i = i + 1;
             if let Some(count) = v.get(i) {
                 let f = Field {
-                    field: String::from_redis_value(name)?,
+                    field: Binary::from_redis_value(name)?,
                     value: FieldValue::Int(i64::from_redis_value(count)?),
                 };
                 i = i + 1;
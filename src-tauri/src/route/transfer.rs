@@ -1,39 +1,153 @@
-use crate::err::CusError;
-use redis::FromRedisValue;
-use serde::Deserialize;
+use crate::{
+    binary::Binary,
+    codec::{self, protobuf},
+    err::CusError,
+    request::IdArgs,
+    sqlite::ProtoSchema,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
-struct RequestResp {
-    ec: u16,
-    em: String,
-    data: Option<String>,
+#[derive(Deserialize)]
+struct PhpArgs {
+    data: Binary,
 }
 
 #[derive(Deserialize)]
-struct PhpArgs {
+struct PhpSerializeArgs {
+    // the json of the value
     data: String,
 }
 
+// the php serialized value to the pretty json
 pub async fn php_unserialize(payload: String) -> Result<String, CusError> {
     let args: PhpArgs = serde_json::from_str(&payload)?;
-    let client = reqwest::Client::new();
-    let params = [("data", args.data.as_str())];
-    let res = client
-        .post("http://www.ecjson.com/phpunserial")
-        .form(&params)
-        .send()
-        .await?;
-    let resp_bytes = res.bytes().await?;
-    let resp = String::from_byte_vec(&resp_bytes).unwrap();
-    if let Some(s) = resp.get(0) {
-        let r: RequestResp = serde_json::from_str(s)?;
-        if r.ec == 200 {
-            if let Some(data) = r.data {
-                return Ok(data);
-            }
-        } else {
-            return Err(CusError::App(r.em));
+    Ok(codec::decode(&args.data.0, Some(&[String::from("php")]), None)?.value)
+}
+
+pub async fn php_serialize(payload: String) -> Result<Binary, CusError> {
+    let args: PhpSerializeArgs = serde_json::from_str(&payload)?;
+    Ok(Binary(codec::encode(
+        &args.data,
+        &[String::from("php")],
+        None,
+    )?))
+}
+
+#[derive(Deserialize)]
+struct ProtobufArgs {
+    value: Binary,
+    // the key name, the schema of its pattern is used
+    name: Option<String>,
+    schema: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProtobufEncodeArgs {
+    // the json of the message
+    value: String,
+    name: Option<String>,
+    schema: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct ProtobufValue {
+    // none if the fields are dumped without the schema
+    schema: Option<ProtoSchema>,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ProtoFile {
+    // the import name, such as user/v1/user.proto
+    name: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ProtoSchemaArgs {
+    pattern: String,
+    message: String,
+    // the .proto sources, or the compiled descriptor set
+    #[serde(default)]
+    files: Vec<ProtoFile>,
+    descriptor: Option<Binary>,
+}
+
+fn schema_of(id: Option<u32>, name: Option<&str>) -> Result<Option<ProtoSchema>, CusError> {
+    match (id, name) {
+        (Some(id), _) => Ok(Some(ProtoSchema::first(id)?)),
+        (None, Some(name)) => ProtoSchema::find(name),
+        _ => Ok(None),
+    }
+}
+
+// the protobuf value to the json by the schema of the key
+// the fields are dumped by their numbers if there is no schema
+pub async fn protobuf(payload: String) -> Result<ProtobufValue, CusError> {
+    let args: ProtobufArgs = serde_json::from_str(&payload)?;
+    let schema = schema_of(args.schema, args.name.as_deref())?;
+    let desc = match &schema {
+        Some(s) => Some(protobuf::descriptor(&s.descriptor, &s.message)?),
+        None => None,
+    };
+    let chain = [String::from("protobuf")];
+    let value = codec::decode(&args.value.0, Some(&chain), desc.as_ref())?.value;
+    Ok(ProtobufValue { schema, value })
+}
+
+pub async fn protobuf_encode(payload: String) -> Result<Binary, CusError> {
+    let args: ProtobufEncodeArgs = serde_json::from_str(&payload)?;
+    let desc = match schema_of(args.schema, args.name.as_deref())? {
+        Some(s) => Some(protobuf::descriptor(&s.descriptor, &s.message)?),
+        None => None,
+    };
+    let chain = [String::from("protobuf")];
+    Ok(Binary(codec::encode(&args.value, &chain, desc.as_ref())?))
+}
+
+pub async fn protobuf_schemas() -> Result<Vec<ProtoSchema>, CusError> {
+    ProtoSchema::all()
+}
+
+pub async fn save_protobuf_schema(payload: String) -> Result<ProtoSchema, CusError> {
+    let args: ProtoSchemaArgs = serde_json::from_str(&payload)?;
+    let descriptor = match args.descriptor {
+        Some(d) => d.0,
+        None if !args.files.is_empty() => {
+            let files: Vec<(String, String)> = args
+                .files
+                .into_iter()
+                .map(|f| (f.name, f.content))
+                .collect();
+            protobuf::compile(&files)?
+        }
+        None => {
+            return Err(CusError::build(
+                "The .proto files or the descriptor set is required",
+            ))
         }
+    };
+    // the message must be in the schema
+    if protobuf::descriptor(&descriptor, &args.message).is_err() {
+        return Err(CusError::App(format!(
+            "Message {} not found, the schema has {}",
+            args.message,
+            protobuf::messages(&descriptor)?.join(", ")
+        )));
     }
-    Err(CusError::App(String::from("php unserialize request error")))
+    let mut schema = ProtoSchema {
+        id: None,
+        pattern: args.pattern,
+        message: args.message,
+        descriptor,
+    };
+    schema.save()?;
+    Ok(schema)
+}
+
+pub async fn del_protobuf_schema(payload: String) -> Result<(), CusError> {
+    let args: IdArgs<u32> = serde_json::from_str(&payload)?;
+    let schema = ProtoSchema::first(args.id)?;
+    schema.del()?;
+    Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::binary::Binary;
 use crate::connection::CValue;
 use crate::request::{CommonValueArgs, FieldValueArgs, FieldValueItem, ItemScanArgs, RangeArgs};
 use crate::response::{Field, ScanLikeResult};
@@ -27,7 +28,7 @@ pub async fn zrem(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<i64, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -41,7 +42,7 @@ pub async fn zrem(
 struct ZAddArgs {
     name: String,
     db: Option<u8>,
-    value: Vec<FieldValueItem<String>>,
+    value: Vec<FieldValueItem<String, Binary>>,
     option_1: Option<String>,
     option_2: Option<String>,
     ch: Option<bool>,
@@ -98,7 +99,7 @@ pub async fn bzmpop(
     if let Some(v) = args.count {
         cmd.arg(("COUNT", v));
     }
-    manager.execute(cid, &mut cmd, args.db).await
+    manager.execute_blocking(cid, &mut cmd, args.db).await
 }
 
 pub async fn bzpop_max(
@@ -108,7 +109,7 @@ pub async fn bzpop_max(
 ) -> Result<CValue, CusError> {
     let args: CommonValueArgs<String, Vec<String>> = serde_json::from_str(&payload)?;
     manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BZPOPMAX").arg(args.name).arg(args.value),
             args.db,
@@ -123,7 +124,7 @@ pub async fn bzpop_min(
 ) -> Result<CValue, CusError> {
     let args: CommonValueArgs<String, Vec<String>> = serde_json::from_str(&payload)?;
     manager
-        .execute(
+        .execute_blocking(
             cid,
             redis::cmd("BZPOPMIN").arg(args.name).arg(args.value),
             args.db,
@@ -202,7 +203,7 @@ pub async fn zincr_by(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<String, Binary> = serde_json::from_str(&payload)?;
     manager
         .execute(
             cid,
@@ -333,7 +334,7 @@ pub async fn zmscore(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs<Vec<String>> = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Vec<Binary>> = serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("ZMSCORE");
     cmd.arg(args.name).arg(args.value);
     manager.execute(cid, &mut cmd, args.db).await
@@ -469,7 +470,7 @@ pub async fn zrank(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs<Option<bool>> = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<Option<bool>, Binary> = serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("ZRANK");
     cmd.arg(args.name).arg(args.field);
 
@@ -519,7 +520,7 @@ pub async fn zrev_rank(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: FieldValueArgs<Option<bool>> = serde_json::from_str(&payload)?;
+    let args: FieldValueArgs<Option<bool>, Binary> = serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("ZREVRANK");
     cmd.arg(args.name).arg(args.field);
 
@@ -536,7 +537,7 @@ pub async fn zscore(
     cid: u32,
     manager: tauri::State<'_, Manager>,
 ) -> Result<CValue, CusError> {
-    let args: CommonValueArgs = serde_json::from_str(&payload)?;
+    let args: CommonValueArgs<Binary> = serde_json::from_str(&payload)?;
     let mut cmd = redis::cmd("ZSCORE");
     cmd.arg(args.name).arg(args.value);
 
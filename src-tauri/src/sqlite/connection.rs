@@ -1,17 +1,24 @@
-use crate::{connection, err::CusError, sqlite, ssh};
-use rusqlite::{self, params, Row};
-use serde::{Deserialize, Serialize};
+use crate::sqlite::{policy::PRODUCTION, secret, Policy, SshHop, Tag, TagRule};
+use crate::{connection, err::CusError, proxy, sqlite, ssh};
+use rusqlite::{self, params, Connection as SqliteConnection, Row, ToSql};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Connection {
     pub id: Option<i64>,
     pub name: Option<String>,
+    #[serde(default)]
     pub host: String,
+    #[serde(default)]
     pub port: u16,
     pub password: Option<String>,
     pub username: Option<String>,
     pub is_cluster: bool,
     pub readonly: bool,
+    // writable even if a tag rule makes it readonly, readonly wins if both are set
+    #[serde(default)]
+    pub writable: bool,
     pub ssh_host: Option<String>,
     pub ssh_port: Option<u16>,
     pub ssh_password: Option<String>,
@@ -19,16 +26,96 @@ pub struct Connection {
     pub ssh_private_key: Option<String>,
     pub ssh_timeout: Option<u32>,
     pub ssh_passphrase: Option<String>,
+    // authenticate with the identities of ssh-agent
+    #[serde(default)]
+    pub ssh_agent: bool,
+    // the jump hosts behind the ssh host, stored in ssh_hops
+    #[serde(default)]
+    pub ssh_hops: Vec<SshHop>,
+    // socks5 or http, the proxy is used if the host is set
+    pub proxy_type: Option<String>,
+    pub proxy_host: Option<String>,
+    pub proxy_port: Option<u16>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    // resp2 or resp3, none means resp2
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub tls: bool,
+    pub tls_ca: Option<String>,
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    #[serde(default)]
+    pub tls_insecure: bool,
+    // the name of the server certificate, the host if it is empty
+    pub tls_server_name: Option<String>,
+    #[serde(default)]
+    pub is_sentinel: bool,
+    pub sentinel_master: Option<String>,
+    pub sentinel_nodes: Option<String>,
+    pub sentinel_password: Option<String>,
+    pub unix_socket: Option<String>,
+    // timeouts in seconds, none means the default, 0 means no timeout
+    pub connect_timeout: Option<u32>,
+    pub response_timeout: Option<u32>,
+    // the commands of the policy need a confirmation
+    pub policy: Option<String>,
+    // the folder, display settings and tags, stored in connection_meta and connection_tags
+    // a field missing in the payload is kept as it is, null clears the nullable ones
+    #[serde(default, deserialize_with = "present")]
+    pub group_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub color: Option<Option<String>>,
+    pub sort: Option<i64>,
+    #[serde(default, deserialize_with = "present")]
+    pub note: Option<Option<String>>,
+    pub tags: Option<Vec<Tag>>,
+}
+
+// some(none) for null, so it can be told from the missing field
+fn present<'de, D, T>(d: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(d).map(Some)
+}
+
+// the default timeouts in seconds
+const CONNECT_TIMEOUT: u32 = 10;
+
+fn to_duration(seconds: Option<u32>, default: Option<u32>) -> Option<Duration> {
+    match seconds.or(default) {
+        Some(0) | None => None,
+        Some(s) => Some(Duration::from_secs(s as u64)),
+    }
 }
 
 impl connection::Connectable for Connection {
     fn get_params(&self) -> connection::ConnectionParams {
-        let redis_params = connection::ConnectedParam {
+        let mut redis_params = connection::ConnectedParam {
             tcp_host: self.host.clone(),
             tcp_port: self.port,
             username: self.username.clone(),
             password: self.password.clone(),
+            tls: None,
+            unix_socket: self.get_unix_socket(),
+            connect_timeout: to_duration(self.connect_timeout, Some(CONNECT_TIMEOUT)),
+            response_timeout: to_duration(self.response_timeout, None),
+            protocol: self.get_protocol(),
         };
+        if self.tls {
+            redis_params.tls = Some(connection::TlsParams {
+                ca: self.tls_ca.clone(),
+                cert: self.tls_cert.clone(),
+                key: self.tls_key.clone(),
+                insecure: self.tls_insecure,
+                server_name: self
+                    .tls_server_name
+                    .clone()
+                    .filter(|s| !s.trim().is_empty()),
+            });
+        }
         let mut ssh_params = None;
         if let Some(ssh_host) = &self.ssh_host {
             let mut port = 22;
@@ -46,15 +133,34 @@ impl connection::Connectable for Connection {
                 password: self.ssh_password.clone(),
                 private_key: self.ssh_private_key.clone(),
                 passphrase: self.ssh_passphrase.clone(),
+                agent: self.ssh_agent,
+                jumps: self.ssh_hops.iter().map(SshHop::get_jump_host).collect(),
+                proxy: self.get_proxy_params(),
                 target_host: self.host.clone(),
                 target_port: self.port,
+                timeout: to_duration(self.ssh_timeout, Some(CONNECT_TIMEOUT)),
             };
             ssh_params = Some(ssh_p);
         }
+        let mut sentinel_params = None;
+        if self.is_sentinel {
+            sentinel_params = Some(connection::SentinelParams {
+                master_name: self
+                    .sentinel_master
+                    .clone()
+                    .unwrap_or(String::from("mymaster")),
+                nodes: self.get_sentinel_nodes(),
+                password: self.sentinel_password.clone(),
+            });
+        }
         connection::ConnectionParams {
             redis_params,
             ssh_params,
+            proxy_params: self.get_proxy_params(),
+            sentinel_params,
             is_cluster: self.is_cluster,
+            readonly: self.get_readonly(),
+            dangerous: self.get_dangerous_commands(),
         }
     }
 }
@@ -89,9 +195,164 @@ impl Connection {
             ssh_private_key: r.get(12).unwrap_or_default(),
             ssh_timeout: r.get(13).unwrap_or_default(),
             ssh_passphrase: r.get(14).unwrap_or_default(),
+            tls: r.get::<_, i64>(15).unwrap_or_default() > 0,
+            tls_ca: r.get(16).unwrap_or_default(),
+            tls_cert: r.get(17).unwrap_or_default(),
+            tls_key: r.get(18).unwrap_or_default(),
+            tls_insecure: r.get::<_, i64>(19).unwrap_or_default() > 0,
+            is_sentinel: r.get::<_, i64>(20).unwrap_or_default() > 0,
+            sentinel_master: r.get(21).unwrap_or_default(),
+            sentinel_nodes: r.get(22).unwrap_or_default(),
+            sentinel_password: r.get(23).unwrap_or_default(),
+            unix_socket: r.get(24).unwrap_or_default(),
+            connect_timeout: r.get(25).unwrap_or_default(),
+            response_timeout: r.get(26).unwrap_or_default(),
+            policy: r.get(27).unwrap_or_default(),
+            ssh_agent: r.get::<_, i64>(28).unwrap_or_default() > 0,
+            proxy_type: r.get(29).unwrap_or_default(),
+            proxy_host: r.get(30).unwrap_or_default(),
+            proxy_port: r.get(31).unwrap_or_default(),
+            proxy_username: r.get(32).unwrap_or_default(),
+            proxy_password: r.get(33).unwrap_or_default(),
+            protocol: r.get(34).unwrap_or_default(),
+            tls_server_name: r.get(35).unwrap_or_default(),
+            writable: r.get::<_, i64>(36).unwrap_or_default() > 0,
+            group_id: Some(r.get(37).unwrap_or_default()),
+            color: Some(r.get(38).unwrap_or_default()),
+            sort: Some(r.get(39).unwrap_or_default()),
+            note: Some(r.get(40).unwrap_or_default()),
+            tags: Some(vec![]),
+            ssh_hops: vec![],
+        }
+    }
+
+    // the tag rules matched by the tags of the connection
+    // if the rules cannot be loaded, the prod tags are treated as the seeded rules
+    fn get_tag_rules(&self) -> Vec<TagRule> {
+        let tags = match &self.tags {
+            Some(tags) if !tags.is_empty() => tags,
+            _ => return vec![],
+        };
+        let rules = TagRule::all().unwrap_or_else(|_| {
+            vec![TagRule {
+                id: None,
+                tag: String::from("env=prod"),
+                readonly: true,
+                policy: Some(String::from("production")),
+            }]
+        });
+        rules.into_iter().filter(|r| r.matches(tags)).collect()
+    }
+
+    // the connection is readonly if it is set, or a tag rule says so and it is not set writable
+    pub fn get_readonly(&self) -> bool {
+        if self.readonly || self.writable {
+            return self.readonly;
+        }
+        self.get_tag_rules().iter().any(|r| r.readonly)
+    }
+
+    // the policy of the connection, or the policy of the tag rules
+    pub fn get_policy(&self) -> Option<String> {
+        match self.policy.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => Some(name.to_string()),
+            _ => self
+                .get_tag_rules()
+                .into_iter()
+                .find_map(|r| r.policy.filter(|p| !p.trim().is_empty())),
+        }
+    }
+
+    // the commands need a confirmation
+    // if the policy cannot be loaded or has been deleted, the commands of production policy are used
+    pub fn get_dangerous_commands(&self) -> Vec<String> {
+        match self.get_policy().map(|name| Policy::commands(&name)) {
+            Some(Ok(commands)) if !commands.is_empty() => commands,
+            Some(_) => PRODUCTION.iter().map(|s| s.to_string()).collect(),
+            None => vec![],
+        }
+    }
+
+    // decrypt the secret columns, they are kept encrypted if locked
+    fn decrypt(&mut self) -> Result<(), CusError> {
+        self.password = secret::decrypt(&self.password)?;
+        self.ssh_password = secret::decrypt(&self.ssh_password)?;
+        self.ssh_private_key = secret::decrypt(&self.ssh_private_key)?;
+        self.ssh_passphrase = secret::decrypt(&self.ssh_passphrase)?;
+        self.sentinel_password = secret::decrypt(&self.sentinel_password)?;
+        self.proxy_password = secret::decrypt(&self.proxy_password)?;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        [
+            &self.password,
+            &self.ssh_password,
+            &self.ssh_private_key,
+            &self.ssh_passphrase,
+            &self.sentinel_password,
+            &self.proxy_password,
+        ]
+        .iter()
+        .any(|v| v.as_deref().is_some_and(secret::is_encrypted))
+            || self.ssh_hops.iter().any(SshHop::is_locked)
+    }
+
+    // the proxy params, none if the proxy host is empty
+    pub fn get_proxy_params(&self) -> Option<proxy::ProxyParams> {
+        let host = self.proxy_host.clone().filter(|h| !h.trim().is_empty())?;
+        let kind = match self.proxy_type.as_deref() {
+            Some("http") => proxy::ProxyKind::Http,
+            _ => proxy::ProxyKind::Socks5,
+        };
+        let port = self.proxy_port.unwrap_or(match kind {
+            proxy::ProxyKind::Http => 8080,
+            proxy::ProxyKind::Socks5 => 1080,
+        });
+        Some(proxy::ProxyParams {
+            kind,
+            host,
+            port,
+            username: self.proxy_username.clone(),
+            password: self.proxy_password.clone(),
+            timeout: to_duration(self.connect_timeout, Some(CONNECT_TIMEOUT)),
+        })
+    }
+
+    // resp3 if it is set as resp3 or 3, resp2 otherwise
+    pub fn get_protocol(&self) -> redis::ProtocolVersion {
+        match self.protocol.as_deref().map(str::to_lowercase).as_deref() {
+            Some("resp3") | Some("3") => redis::ProtocolVersion::RESP3,
+            _ => redis::ProtocolVersion::RESP2,
         }
     }
 
+    // the unix socket path, an empty path means tcp
+    pub fn get_unix_socket(&self) -> Option<String> {
+        self.unix_socket.clone().filter(|s| !s.trim().is_empty())
+    }
+
+    // in sentinel mode host/port is the first sentinel,
+    // sentinel_nodes holds the others as host:port separated by comma
+    pub fn get_sentinel_nodes(&self) -> Vec<(String, u16)> {
+        let mut nodes = vec![(self.host.clone(), self.port)];
+        if let Some(s) = &self.sentinel_nodes {
+            for item in s.split(",") {
+                let item = item.trim();
+                if item.is_empty() {
+                    continue;
+                }
+                match item.rsplit_once(":") {
+                    Some((host, port)) => {
+                        nodes.push((host.to_string(), port.parse().unwrap_or(26379)))
+                    }
+                    None => nodes.push((item.to_string(), 26379)),
+                }
+            }
+        }
+        nodes
+    }
+
     pub fn first(id: u32) -> Result<Connection, CusError> {
         let conn = sqlite::get_client()?;
         let mut stmt = conn.prepare(
@@ -109,15 +370,65 @@ impl Connection {
             ssh_username,
             ssh_private_key,
             ssh_timeout,
-            ssh_passphrase
-            from connections where id= ?1",
+            ssh_passphrase,
+            tls,
+            tls_ca,
+            tls_cert,
+            tls_key,
+            tls_insecure,
+            is_sentinel,
+            sentinel_master,
+            sentinel_nodes,
+            sentinel_password,
+            unix_socket,
+            connect_timeout,
+            response_timeout,
+            policy,
+            ssh_agent,
+            proxy_type,
+            proxy_host,
+            proxy_port,
+            proxy_username,
+            proxy_password,
+            protocol,
+            tls_server_name,
+            writable,
+            m.group_id,
+            m.color,
+            m.sort,
+            m.note
+            from connections
+            left join connection_meta m on m.connection_id = connections.id
+            where id= ?1",
         )?;
-        let c = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        let mut c = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        c.tags = Some(Tag::of(id as i64)?);
+        c.ssh_hops = SshHop::of(id as i64)?;
+        c.decrypt()?;
+        if c.is_locked() {
+            return Err(CusError::locked());
+        }
         Ok(c)
     }
 
     pub fn save(&mut self) -> Result<(), CusError> {
         let conn = sqlite::get_client()?;
+        self.save_with(&conn)
+    }
+
+    // save the connections in one transaction, none is saved if one fails
+    pub fn save_all(connections: &mut [Connection]) -> Result<(), CusError> {
+        let mut conn = sqlite::get_client()?;
+        let tx = conn.transaction()?;
+        for c in connections.iter_mut() {
+            c.save_with(&tx)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn save_with(&mut self, conn: &SqliteConnection) -> Result<(), CusError> {
+        Policy::check(&self.policy)?;
         let mut is_cluster = 0;
         if self.is_cluster {
             is_cluster = 1;
@@ -126,8 +437,29 @@ impl Connection {
         if self.readonly {
             readonly = 1;
         }
+        let tls = self.tls as i64;
+        let tls_insecure = self.tls_insecure as i64;
+        let is_sentinel = self.is_sentinel as i64;
+        let password = secret::encrypt(&self.password)?;
+        let ssh_password = secret::encrypt(&self.ssh_password)?;
+        // the private key is stored inline, so the connection does not depend on the file
+        // the key still encrypted while locked is not a path, it is kept as it is
+        if !self
+            .ssh_private_key
+            .as_deref()
+            .is_some_and(secret::is_encrypted)
+        {
+            self.ssh_private_key = ssh::get_private_key(&self.ssh_private_key)?;
+        }
+        let ssh_private_key = secret::encrypt(&self.ssh_private_key)?;
+        let ssh_passphrase = secret::encrypt(&self.ssh_passphrase)?;
+        let sentinel_password = secret::encrypt(&self.sentinel_password)?;
+        let proxy_password = secret::encrypt(&self.proxy_password)?;
         if self.name.is_none() {
-            self.name = Some(format!("{}:{}", self.host, self.port))
+            match self.get_unix_socket() {
+                Some(path) => self.name = Some(path),
+                None => self.name = Some(format!("{}:{}", self.host, self.port)),
+            }
         }
         if let Some(id) = self.id {
             conn.execute(
@@ -145,23 +477,67 @@ impl Connection {
                 ssh_username =?11,
                 ssh_private_key =?12,
                 ssh_timeout =?13,
-                ssh_passphrase =?14 
-                where id = ?15",
+                ssh_passphrase =?14,
+                tls =?15,
+                tls_ca =?16,
+                tls_cert =?17,
+                tls_key =?18,
+                tls_insecure =?19,
+                is_sentinel =?20,
+                sentinel_master =?21,
+                sentinel_nodes =?22,
+                sentinel_password =?23,
+                unix_socket =?24,
+                connect_timeout =?25,
+                response_timeout =?26,
+                policy =?27,
+                ssh_agent =?28,
+                proxy_type =?29,
+                proxy_host =?30,
+                proxy_port =?31,
+                proxy_username =?32,
+                proxy_password =?33,
+                protocol =?34,
+                tls_server_name =?35,
+                writable =?36
+                where id = ?37",
                 params!(
                     self.name,
                     self.host,
                     self.port,
-                    self.password,
+                    &password,
                     self.username,
                     is_cluster,
                     readonly,
                     self.ssh_host,
                     self.ssh_port,
-                    self.ssh_password,
+                    &ssh_password,
                     self.ssh_username,
-                    self.ssh_private_key,
+                    &ssh_private_key,
                     self.ssh_timeout,
-                    self.ssh_passphrase,
+                    &ssh_passphrase,
+                    tls,
+                    self.tls_ca,
+                    self.tls_cert,
+                    self.tls_key,
+                    tls_insecure,
+                    is_sentinel,
+                    self.sentinel_master,
+                    self.sentinel_nodes,
+                    &sentinel_password,
+                    self.unix_socket,
+                    self.connect_timeout,
+                    self.response_timeout,
+                    self.policy,
+                    self.ssh_agent as i64,
+                    self.proxy_type,
+                    self.proxy_host,
+                    self.proxy_port,
+                    self.proxy_username,
+                    &proxy_password,
+                    self.protocol,
+                    self.tls_server_name,
+                    self.writable as i64,
                     id
                 ),
             )?;
@@ -181,27 +557,115 @@ impl Connection {
                     ssh_username,
                     ssh_private_key,
                     ssh_timeout,
-                    ssh_passphrase
-                    ) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                    ssh_passphrase,
+                    tls,
+                    tls_ca,
+                    tls_cert,
+                    tls_key,
+                    tls_insecure,
+                    is_sentinel,
+                    sentinel_master,
+                    sentinel_nodes,
+                    sentinel_password,
+                    unix_socket,
+                    connect_timeout,
+                    response_timeout,
+                    policy,
+                    ssh_agent,
+                    proxy_type,
+                    proxy_host,
+                    proxy_port,
+                    proxy_username,
+                    proxy_password,
+                    protocol,
+                    tls_server_name,
+                    writable
+                    ) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36)",
                 params!(
                     &self.name,
                     &self.host,
                     &self.port,
-                    &self.password,
+                    &password,
                     &self.username,
                     is_cluster,
                     readonly,
                     self.ssh_host,
                     self.ssh_port,
-                    self.ssh_password,
+                    &ssh_password,
                     self.ssh_username,
-                    self.ssh_private_key,
+                    &ssh_private_key,
                     self.ssh_timeout,
-                    self.ssh_passphrase,
+                    &ssh_passphrase,
+                    tls,
+                    self.tls_ca,
+                    self.tls_cert,
+                    self.tls_key,
+                    tls_insecure,
+                    is_sentinel,
+                    self.sentinel_master,
+                    self.sentinel_nodes,
+                    &sentinel_password,
+                    self.unix_socket,
+                    self.connect_timeout,
+                    self.response_timeout,
+                    self.policy,
+                    self.ssh_agent as i64,
+                    self.proxy_type,
+                    self.proxy_host,
+                    self.proxy_port,
+                    self.proxy_username,
+                    &proxy_password,
+                    self.protocol,
+                    self.tls_server_name,
+                    self.writable as i64,
                 ),
             )?;
             self.id = Some(conn.last_insert_rowid());
         }
+        if let Some(id) = self.id {
+            SshHop::save(conn, id, &mut self.ssh_hops)?;
+        }
+        self.save_meta(conn)
+    }
+
+    // save the folder, display settings and tags of the connection
+    // only the fields in the payload are written, so an edit without them keeps them
+    fn save_meta(&self, conn: &SqliteConnection) -> Result<(), CusError> {
+        let id = match self.id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let mut fields: Vec<(&str, &dyn ToSql)> = vec![];
+        if let Some(v) = &self.group_id {
+            fields.push(("group_id", v));
+        }
+        if let Some(v) = &self.color {
+            fields.push(("color", v));
+        }
+        if let Some(v) = &self.sort {
+            fields.push(("sort", v));
+        }
+        if let Some(v) = &self.note {
+            fields.push(("note", v));
+        }
+        if !fields.is_empty() {
+            conn.execute(
+                "insert or ignore into connection_meta(connection_id) values(?1)",
+                [id],
+            )?;
+        }
+        for (column, value) in fields {
+            conn.execute(
+                &format!(
+                    "update connection_meta set {} = ?1 where connection_id = ?2",
+                    column
+                ),
+                params!(value, id),
+            )?;
+        }
+        if let Some(tags) = &self.tags {
+            Tag::save(conn, id, tags)?;
+        }
         Ok(())
     }
 
@@ -212,6 +676,15 @@ impl Connection {
             "delete from collections where connection_id = ?1",
             [self.id],
         )?;
+        conn.execute(
+            "delete from connection_meta where connection_id = ?1",
+            [self.id],
+        )?;
+        conn.execute(
+            "delete from connection_tags where connection_id = ?1",
+            [self.id],
+        )?;
+        conn.execute("delete from ssh_hops where connection_id = ?1", [self.id])?;
         Ok(())
     }
 
@@ -232,13 +705,49 @@ impl Connection {
                 ssh_username,
                 ssh_private_key,
                 ssh_timeout,
-                ssh_passphrase
-                from connections",
+                ssh_passphrase,
+                tls,
+                tls_ca,
+                tls_cert,
+                tls_key,
+                tls_insecure,
+                is_sentinel,
+                sentinel_master,
+                sentinel_nodes,
+                sentinel_password,
+                unix_socket,
+                connect_timeout,
+                response_timeout,
+                policy,
+                ssh_agent,
+                proxy_type,
+                proxy_host,
+                proxy_port,
+                proxy_username,
+                proxy_password,
+                protocol,
+                tls_server_name,
+                writable,
+                m.group_id,
+                m.color,
+                m.sort,
+                m.note
+                from connections
+                left join connection_meta m on m.connection_id = connections.id
+                order by m.sort, id",
         )?;
         let connections_result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut tags = Tag::all()?;
+        let mut hops = SshHop::all()?;
         let mut result: Vec<Connection> = vec![];
         for x in connections_result.into_iter() {
-            result.push(x?);
+            let mut c = x?;
+            if let Some(id) = c.id {
+                c.tags = Some(tags.remove(&id).unwrap_or_default());
+                c.ssh_hops = hops.remove(&id).unwrap_or_default();
+            }
+            c.decrypt()?;
+            result.push(c);
         }
         Ok(result)
     }
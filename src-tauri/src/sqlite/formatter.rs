@@ -0,0 +1,101 @@
+use rusqlite::{self, params, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::{err::CusError, sqlite};
+
+// the codec chain remembered for the keys matched by the pattern
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Formatter {
+    pub id: Option<i64>,
+    pub pattern: String,
+    pub chain: Vec<String>,
+}
+
+impl Formatter {
+    pub fn build(r: &Row) -> Formatter {
+        let chain: String = r.get(2).unwrap();
+        Formatter {
+            id: r.get(0).unwrap(),
+            pattern: r.get(1).unwrap(),
+            chain: chain
+                .split(",")
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    pub fn first(id: u32) -> Result<Formatter, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            pattern,
+            chain
+            from formatters where id= ?1",
+        )?;
+        let f = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(f)
+    }
+
+    // the formatter of the same pattern is replaced
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        if self.pattern.is_empty() {
+            return Err(CusError::build("The pattern is required"));
+        }
+        if let Some(id) = self.id {
+            conn.execute(
+                "delete from formatters where pattern = ?1 and id != ?2",
+                params!(&self.pattern, id),
+            )?;
+            conn.execute(
+                "UPDATE formatters set
+                pattern= ?1,
+                chain= ?2
+                where id = ?3",
+                params!(&self.pattern, self.chain.join(","), id),
+            )?;
+        } else {
+            conn.execute(
+                "insert or replace into formatters(
+                pattern,
+                chain
+                ) values(?1, ?2)",
+                params!(&self.pattern, self.chain.join(",")),
+            )?;
+            self.id = Some(conn.last_insert_rowid());
+        }
+        Ok(())
+    }
+
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute("delete from formatters where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<Formatter>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            pattern,
+            chain
+            from formatters order by pattern",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut formatters: Vec<Formatter> = vec![];
+        for x in result.into_iter() {
+            formatters.push(x?);
+        }
+        Ok(formatters)
+    }
+
+    // the formatter of the key
+    pub fn find(key: &str) -> Result<Option<Formatter>, CusError> {
+        sqlite::find_pattern("formatters", key)?
+            .map(Self::first)
+            .transpose()
+    }
+}
@@ -0,0 +1,125 @@
+use rusqlite::{self, params, Connection as SqliteConnection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::{err::CusError, sqlite};
+
+// the folder of the connections, the folders can be nested by parent_id
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Group {
+    pub id: Option<i64>,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub sort: i64,
+}
+
+impl Group {
+    pub fn build(r: &Row) -> Group {
+        Group {
+            id: r.get(0).unwrap(),
+            name: r.get(1).unwrap(),
+            parent_id: r.get(2).unwrap_or_default(),
+            sort: r.get(3).unwrap_or_default(),
+        }
+    }
+
+    pub fn first(id: u32) -> Result<Group, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            name,
+            parent_id,
+            sort
+            from connection_groups where id= ?1",
+        )?;
+        let g = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(g)
+    }
+
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        if let Some(id) = self.id {
+            self.check_parent(&conn, id)?;
+            conn.execute(
+                "UPDATE connection_groups set
+                name= ?1,
+                parent_id= ?2,
+                sort= ?3
+                where id = ?4",
+                params!(&self.name, self.parent_id, self.sort, id),
+            )?;
+        } else {
+            conn.execute(
+                "insert into connection_groups(
+                name,
+                parent_id,
+                sort
+                ) values(?1, ?2, ?3)",
+                params!(&self.name, self.parent_id, self.sort),
+            )?;
+            self.id = Some(conn.last_insert_rowid());
+        }
+        Ok(())
+    }
+
+    // walk up from the new parent, the folder must not be found on the way
+    fn check_parent(&self, conn: &SqliteConnection, id: i64) -> Result<(), CusError> {
+        let mut parent = self.parent_id;
+        let mut visited = HashSet::new();
+        while let Some(p) = parent {
+            if p == id {
+                return Err(CusError::build(
+                    "A folder cannot be moved into itself or its subfolders",
+                ));
+            }
+            // the chain saved before may already be broken
+            if !visited.insert(p) {
+                break;
+            }
+            parent = conn
+                .query_row(
+                    "select parent_id from connection_groups where id = ?1",
+                    [p],
+                    |r| r.get(0),
+                )
+                .optional()?
+                .flatten();
+        }
+        Ok(())
+    }
+
+    // the children and the connections are moved to the parent folder
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute(
+            "update connection_groups set parent_id = ?1 where parent_id = ?2",
+            params!(self.parent_id, self.id),
+        )?;
+        conn.execute(
+            "update connection_meta set group_id = ?1 where group_id = ?2",
+            params!(self.parent_id, self.id),
+        )?;
+        conn.execute("delete from connection_groups where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<Group>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            name,
+            parent_id,
+            sort
+            from connection_groups order by sort, id",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut groups: Vec<Group> = vec![];
+        for x in result.into_iter() {
+            groups.push(x?);
+        }
+        Ok(groups)
+    }
+}
@@ -0,0 +1,116 @@
+use rusqlite::{self, params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::{err::CusError, sqlite};
+
+// the ssh host key trusted by the user, the fingerprint is the base64 of sha256
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KnownHost {
+    pub id: Option<i64>,
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub created_at: Option<String>,
+}
+
+impl KnownHost {
+    pub fn build(r: &Row) -> KnownHost {
+        KnownHost {
+            id: r.get(0).unwrap(),
+            host: r.get(1).unwrap(),
+            port: r.get(2).unwrap(),
+            key_type: r.get(3).unwrap(),
+            fingerprint: r.get(4).unwrap(),
+            created_at: r.get(5).unwrap_or_default(),
+        }
+    }
+
+    pub fn changed(host: &str, port: u16) -> CusError {
+        CusError::App(format!(
+            "Host key verification failed, the host key of {}:{} has changed",
+            host, port
+        ))
+    }
+
+    pub fn first(id: u32) -> Result<KnownHost, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            host,
+            port,
+            key_type,
+            fingerprint,
+            created_at
+            from known_hosts where id= ?1",
+        )?;
+        let h = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(h)
+    }
+
+    pub fn find(host: &str, port: u16) -> Result<Option<KnownHost>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            host,
+            port,
+            key_type,
+            fingerprint,
+            created_at
+            from known_hosts where host= ?1 and port = ?2",
+        )?;
+        let h = stmt
+            .query_row(params!(host, port), |r| Ok(Self::build(r)))
+            .optional()?;
+        Ok(h)
+    }
+
+    // trust the host key, the old key of the host is replaced
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        self.fingerprint = self
+            .fingerprint
+            .trim()
+            .trim_start_matches("SHA256:")
+            .to_string();
+        conn.execute(
+            "insert or replace into known_hosts(
+                host,
+                port,
+                key_type,
+                fingerprint
+                ) values(?1, ?2, ?3, ?4)",
+            params!(&self.host, self.port, &self.key_type, &self.fingerprint),
+        )?;
+        self.id = Some(conn.last_insert_rowid());
+        Ok(())
+    }
+
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute("delete from known_hosts where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<KnownHost>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            host,
+            port,
+            key_type,
+            fingerprint,
+            created_at
+            from known_hosts order by host, port",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut hosts: Vec<KnownHost> = vec![];
+        for x in result.into_iter() {
+            hosts.push(x?);
+        }
+        Ok(hosts)
+    }
+}
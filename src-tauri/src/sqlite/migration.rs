@@ -0,0 +1,365 @@
+use crate::{err::CusError, sqlite::policy};
+use rusqlite::Connection as SqliteConnection;
+
+type Migration = fn(&SqliteConnection) -> Result<(), CusError>;
+
+// the ordered migrations, the schema version is the count of the applied migrations
+// a released migration should never be changed, add a new one instead
+// the databases created before the versioning have some of the columns, so every step is idempotent
+const MIGRATIONS: [Migration; 16] = [
+    create_tables,
+    add_tls,
+    add_sentinel,
+    add_unix_socket,
+    add_timeouts,
+    add_policies,
+    add_settings,
+    add_groups,
+    add_known_hosts,
+    add_ssh_hops,
+    add_proxy,
+    add_protocol,
+    add_formatters,
+    add_proto_schemas,
+    add_tls_server_name,
+    add_writable,
+];
+
+// run the migrations not applied yet, each one in a transaction
+pub fn run(client: &mut SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+        version INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+    let version: i64 = client.query_row(
+        "select coalesce(max(version), 0) from schema_version",
+        [],
+        |r| r.get(0),
+    )?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = client.transaction()?;
+        migration(&tx)?;
+        tx.execute(
+            "insert into schema_version(version) values(?1)",
+            [i as i64 + 1],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn table_exists(client: &SqliteConnection, table: &str) -> Result<bool, CusError> {
+    let count: i64 = client.query_row(
+        "select count(*) from sqlite_master where type = 'table' and name = ?1",
+        [table],
+        |r| r.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+// add the missing columns to an existing table, so the saved data is kept
+fn add_columns(
+    client: &SqliteConnection,
+    table: &str,
+    columns: &[(&str, &str)],
+) -> Result<(), CusError> {
+    let mut stmt = client.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect::<Result<Vec<String>, _>>()?;
+    for (name, definition) in columns {
+        if !exists.iter().any(|c| c == name) {
+            client.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition),
+                (),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn create_tables(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS connections (
+            id    INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            host  TEXT NOT NULL,
+            port  INTEGER NOT NULL,
+            username TEXT,
+            password  TEXT,
+            is_cluster INTEGER NOT NULL DEFAULT 0,
+            readonly INTEGER NOT NULL DEFAULT 0,
+            ssh_host  TEXT,
+            ssh_port  INTEGER,
+            ssh_password  TEXT,
+            ssh_username  TEXT,
+            ssh_private_key  TEXT,
+            ssh_timeout  INTEGER,
+            ssh_passphrase  TEXT
+        )",
+        (),
+    )?;
+    // the data files imported from the older versions may lack some columns
+    add_columns(
+        client,
+        "connections",
+        &[
+            ("username", "TEXT"),
+            ("readonly", "INTEGER NOT NULL DEFAULT 0"),
+            ("ssh_host", "TEXT"),
+            ("ssh_port", "INTEGER"),
+            ("ssh_password", "TEXT"),
+            ("ssh_username", "TEXT"),
+            ("ssh_private_key", "TEXT"),
+            ("ssh_timeout", "INTEGER"),
+            ("ssh_passphrase", "TEXT"),
+        ],
+    )?;
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS collections (
+        id    INTEGER PRIMARY KEY,
+        connection_id INTEGER NOT NULL,
+        name  TEXT NOT NULL,
+        db  INTEGER,
+        types TEXT NOT NULL,
+        key TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn add_tls(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[
+            ("tls", "INTEGER NOT NULL DEFAULT 0"),
+            ("tls_ca", "TEXT"),
+            ("tls_cert", "TEXT"),
+            ("tls_key", "TEXT"),
+            ("tls_insecure", "INTEGER NOT NULL DEFAULT 0"),
+        ],
+    )
+}
+
+fn add_sentinel(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[
+            ("is_sentinel", "INTEGER NOT NULL DEFAULT 0"),
+            ("sentinel_master", "TEXT"),
+            ("sentinel_nodes", "TEXT"),
+            ("sentinel_password", "TEXT"),
+        ],
+    )
+}
+
+fn add_unix_socket(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(client, "connections", &[("unix_socket", "TEXT")])
+}
+
+fn add_timeouts(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[
+            ("connect_timeout", "INTEGER"),
+            ("response_timeout", "INTEGER"),
+        ],
+    )
+}
+
+// the production policy is filled when the table is created
+fn add_policies(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(client, "connections", &[("policy", "TEXT")])?;
+    if table_exists(client, "policies")? {
+        return Ok(());
+    }
+    client.execute(
+        "CREATE TABLE policies (
+        id    INTEGER PRIMARY KEY,
+        name  TEXT NOT NULL,
+        command TEXT NOT NULL
+    )",
+        (),
+    )?;
+    for command in policy::PRODUCTION {
+        client.execute(
+            "insert into policies(name, command) values(?1, ?2)",
+            ["production", command],
+        )?;
+    }
+    Ok(())
+}
+
+fn add_settings(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+// the folders, tags and display settings of the connections
+// the tag rules are seeded with the prod tags as readonly production connections
+fn add_groups(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS connection_groups (
+        id        INTEGER PRIMARY KEY,
+        name      TEXT NOT NULL,
+        parent_id INTEGER,
+        sort      INTEGER NOT NULL DEFAULT 0
+    )",
+        (),
+    )?;
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS connection_meta (
+        connection_id INTEGER PRIMARY KEY,
+        group_id      INTEGER,
+        color         TEXT,
+        sort          INTEGER NOT NULL DEFAULT 0,
+        note          TEXT
+    )",
+        (),
+    )?;
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS connection_tags (
+        id            INTEGER PRIMARY KEY,
+        connection_id INTEGER NOT NULL,
+        name          TEXT NOT NULL,
+        value         TEXT
+    )",
+        (),
+    )?;
+    if table_exists(client, "tag_rules")? {
+        return Ok(());
+    }
+    client.execute(
+        "CREATE TABLE tag_rules (
+        id       INTEGER PRIMARY KEY,
+        tag      TEXT NOT NULL,
+        readonly INTEGER NOT NULL DEFAULT 0,
+        policy   TEXT
+    )",
+        (),
+    )?;
+    for tag in ["prod", "env=prod", "env=production"] {
+        client.execute(
+            "insert into tag_rules(tag, readonly, policy) values(?1, 1, ?2)",
+            [tag, "production"],
+        )?;
+    }
+    Ok(())
+}
+
+// the ssh hosts trusted on first use, and the ssh-agent authentication
+fn add_known_hosts(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[("ssh_agent", "INTEGER NOT NULL DEFAULT 0")],
+    )?;
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS known_hosts (
+        id          INTEGER PRIMARY KEY,
+        host        TEXT NOT NULL,
+        port        INTEGER NOT NULL,
+        key_type    TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        created_at  TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE(host, port)
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+// the jump hosts behind the ssh host of the connection
+fn add_ssh_hops(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS ssh_hops (
+        id            INTEGER PRIMARY KEY,
+        connection_id INTEGER NOT NULL,
+        sort          INTEGER NOT NULL DEFAULT 0,
+        host          TEXT NOT NULL,
+        port          INTEGER,
+        username      TEXT,
+        password      TEXT,
+        private_key   TEXT,
+        passphrase    TEXT,
+        agent         INTEGER NOT NULL DEFAULT 0
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn add_proxy(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[
+            ("proxy_type", "TEXT"),
+            ("proxy_host", "TEXT"),
+            ("proxy_port", "INTEGER"),
+            ("proxy_username", "TEXT"),
+            ("proxy_password", "TEXT"),
+        ],
+    )
+}
+
+fn add_protocol(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(client, "connections", &[("protocol", "TEXT")])
+}
+
+// the codec chain of the keys by the pattern
+fn add_formatters(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS formatters (
+        id         INTEGER PRIMARY KEY,
+        pattern    TEXT NOT NULL UNIQUE,
+        chain      TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+// the protobuf descriptors of the keys by the pattern
+fn add_proto_schemas(client: &SqliteConnection) -> Result<(), CusError> {
+    client.execute(
+        "CREATE TABLE IF NOT EXISTS proto_schemas (
+        id         INTEGER PRIMARY KEY,
+        pattern    TEXT NOT NULL UNIQUE,
+        message    TEXT NOT NULL,
+        descriptor BLOB NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+        (),
+    )?;
+    Ok(())
+}
+
+// the name the server certificate is checked against
+fn add_tls_server_name(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(client, "connections", &[("tls_server_name", "TEXT")])
+}
+
+// the connection kept writable against the readonly tag rules
+fn add_writable(client: &SqliteConnection) -> Result<(), CusError> {
+    add_columns(
+        client,
+        "connections",
+        &[("writable", "INTEGER NOT NULL DEFAULT 0")],
+    )
+}
@@ -1,15 +1,34 @@
-use crate::err::CusError;
+use crate::{err::CusError, utils};
 use dirs_next;
 use rusqlite::{self, Connection as SqliteConnection};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 const DATA_DIR: &str = "redis";
+// the schema changes are applied by the migrations, do not bump the name any more
 const DATA_NAME: &str = "data3.db";
 
 mod collection;
 mod connection;
+mod formatter;
+mod group;
+mod known_host;
+mod migration;
+mod policy;
+mod proto_schema;
+pub mod secret;
+mod ssh_hop;
+mod tag;
 
 pub use collection::Collection;
 pub use connection::Connection;
+pub use formatter::Formatter;
+pub use group::Group;
+pub use known_host::KnownHost;
+pub use policy::Policy;
+pub use proto_schema::ProtoSchema;
+pub use ssh_hop::SshHop;
+pub use tag::{Tag, TagRule};
 
 pub fn get_client() -> Result<SqliteConnection, CusError> {
     let path = get_data_path();
@@ -17,47 +36,76 @@ pub fn get_client() -> Result<SqliteConnection, CusError> {
     Ok(conn)
 }
 
+// the id of the row whose pattern matches the key, the longest pattern is the most specific one
+// only the patterns are loaded, the row is loaded by the id then
+fn find_pattern(table: &str, key: &str) -> Result<Option<u32>, CusError> {
+    let conn = get_client()?;
+    let mut stmt = conn.prepare(&format!(
+        "select id, pattern from {} order by pattern",
+        table
+    ))?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, u32>(0)?, r.get::<_, String>(1)?)))?;
+    let mut found: Option<(u32, String)> = None;
+    for row in rows {
+        let (id, pattern) = row?;
+        if !utils::glob_match(&pattern, key) {
+            continue;
+        }
+        let longer = match &found {
+            Some((_, p)) => pattern.len() >= p.len(),
+            None => true,
+        };
+        if longer {
+            found = Some((id, pattern));
+        }
+    }
+    Ok(found.map(|(id, _)| id))
+}
+
 pub fn init() {
-    let client = get_client().unwrap();
-    client
-        .execute(
-            "CREATE TABLE IF NOT EXISTS connections (
-            id    INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            host  TEXT NOT NULL,
-            port  INTEGER NOT NULL,
-            username TEXT,
-            password  TEXT,
-            is_cluster INTEGER NOT NULL DEFAULT 0,
-            readonly INTEGER NOT NULL DEFAULT 0,
-            ssh_host  TEXT,
-            ssh_port  INTEGER,
-            ssh_password  TEXT,
-            ssh_username  TEXT,
-            ssh_private_key  TEXT,
-            ssh_timeout  INTEGER,
-            ssh_passphrase  TEXT
-        )",
-            (), // empty list of parameters.
-        )
-        .unwrap();
-    client
-        .execute(
-            "CREATE TABLE IF NOT EXISTS collections (
-        id    INTEGER PRIMARY KEY,
-        connection_id INTEGER NOT NULL,
-        name  TEXT NOT NULL,
-        db  INTEGER,
-        types TEXT NOT NULL,
-        key TEXT NOT NULL,
-        created_at TEXT NOT NULL
-    )",
-            (), // empty list of parameters.
-        )
-        .unwrap();
+    import_legacy().unwrap();
+    let mut client = get_client().unwrap();
+    migration::run(&mut client).unwrap();
+}
+
+// the data file name was bumped by the schema changes before the migrations,
+// copy the newest older data file if the current one is not created yet
+fn import_legacy() -> Result<(), CusError> {
+    let path = get_data_path();
+    if Path::new(&path).exists() {
+        return Ok(());
+    }
+    let dir = get_data_dir();
+    let mut legacy: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("data") || !name.ends_with(".db") || name == DATA_NAME {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        let newer = match &legacy {
+            Some((t, _)) => modified > *t,
+            None => true,
+        };
+        if newer {
+            legacy = Some((modified, entry.path()));
+        }
+    }
+    if let Some((_, legacy_path)) = legacy {
+        fs::copy(legacy_path, path)?;
+    }
+    Ok(())
 }
 
 fn get_data_path() -> String {
+    let mut full_dir = get_data_dir();
+    full_dir.push_str("/");
+    full_dir.push_str(DATA_NAME);
+    full_dir
+}
+
+fn get_data_dir() -> String {
     if let Some(data_dir) = dirs_next::data_dir() {
         let mut full_dir: String = String::from(data_dir.to_str().unwrap());
         full_dir.push_str("/");
@@ -69,8 +117,6 @@ fn get_data_path() -> String {
             }
             _ => {}
         }
-        full_dir.push_str("/");
-        full_dir.push_str(DATA_NAME);
         return full_dir;
     } else {
         panic!("sqlite error: data dir not exists")
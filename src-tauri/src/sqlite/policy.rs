@@ -0,0 +1,116 @@
+use rusqlite::{self, params, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::{err::CusError, sqlite};
+
+// the commands need a confirmation under the policy
+// a command is the lowercase name, or container|subcommand
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Policy {
+    pub id: Option<i64>,
+    pub name: String,
+    pub command: String,
+}
+
+// the default commands of production policy
+pub const PRODUCTION: [&str; 17] = [
+    "flushall",
+    "flushdb",
+    "shutdown",
+    "keys",
+    "debug",
+    "swapdb",
+    "save",
+    "replicaof",
+    "slaveof",
+    "client|kill",
+    "config|set",
+    "config|rewrite",
+    "config|resetstat",
+    "memory|purge",
+    "script|flush",
+    "function|flush",
+    "cluster|reset",
+];
+
+impl Policy {
+    pub fn build(r: &Row) -> Policy {
+        Policy {
+            id: r.get(0).unwrap(),
+            name: r.get(1).unwrap(),
+            command: r.get(2).unwrap(),
+        }
+    }
+
+    pub fn first(id: u32) -> Result<Policy, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            name,
+            command
+            from policies where id= ?1",
+        )?;
+        let c = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(c)
+    }
+
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        self.command = self.command.trim().to_lowercase();
+        conn.execute(
+            "insert into policies(
+                name,
+                command
+                ) values(?1, ?2)",
+            params!(&self.name, &self.command),
+        )?;
+        self.id = Some(conn.last_insert_rowid());
+        Ok(())
+    }
+
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute("delete from policies where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<Policy>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            name,
+            command
+            from policies order by name, command",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut policies: Vec<Policy> = vec![];
+        for x in result.into_iter() {
+            policies.push(x?);
+        }
+        Ok(policies)
+    }
+
+    // the commands of the policy
+    pub fn commands(name: &str) -> Result<Vec<String>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare("select command from policies where name = ?1")?;
+        let result = stmt.query_map([name], |row| row.get::<_, String>(0))?;
+        let mut commands: Vec<String> = vec![];
+        for x in result.into_iter() {
+            commands.push(x?);
+        }
+        Ok(commands)
+    }
+
+    // an unknown policy would protect nothing, so it is rejected when it is set
+    pub fn check(name: &Option<String>) -> Result<(), CusError> {
+        match name.as_deref().map(str::trim) {
+            Some(n) if !n.is_empty() && Self::commands(n)?.is_empty() => {
+                Err(CusError::App(format!("Unknown policy {}", n)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
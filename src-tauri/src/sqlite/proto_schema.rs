@@ -0,0 +1,105 @@
+use rusqlite::{self, params, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::{err::CusError, sqlite};
+
+// the protobuf message of the keys matched by the pattern
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProtoSchema {
+    pub id: Option<i64>,
+    pub pattern: String,
+    // the full name of the message, such as package.User
+    pub message: String,
+    // the encoded FileDescriptorSet
+    #[serde(skip)]
+    pub descriptor: Vec<u8>,
+}
+
+impl ProtoSchema {
+    pub fn build(r: &Row) -> ProtoSchema {
+        ProtoSchema {
+            id: r.get(0).unwrap(),
+            pattern: r.get(1).unwrap(),
+            message: r.get(2).unwrap(),
+            descriptor: r.get(3).unwrap(),
+        }
+    }
+
+    pub fn first(id: u32) -> Result<ProtoSchema, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            pattern,
+            message,
+            descriptor
+            from proto_schemas where id= ?1",
+        )?;
+        let s = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(s)
+    }
+
+    // the schema of the same pattern is replaced
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        if self.pattern.is_empty() {
+            return Err(CusError::build("The pattern is required"));
+        }
+        if let Some(id) = self.id {
+            conn.execute(
+                "delete from proto_schemas where pattern = ?1 and id != ?2",
+                params!(&self.pattern, id),
+            )?;
+            conn.execute(
+                "UPDATE proto_schemas set
+                pattern= ?1,
+                message= ?2,
+                descriptor= ?3
+                where id = ?4",
+                params!(&self.pattern, &self.message, &self.descriptor, id),
+            )?;
+        } else {
+            conn.execute(
+                "insert or replace into proto_schemas(
+                pattern,
+                message,
+                descriptor
+                ) values(?1, ?2, ?3)",
+                params!(&self.pattern, &self.message, &self.descriptor),
+            )?;
+            self.id = Some(conn.last_insert_rowid());
+        }
+        Ok(())
+    }
+
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute("delete from proto_schemas where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<ProtoSchema>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            pattern,
+            message,
+            descriptor
+            from proto_schemas order by pattern",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut schemas: Vec<ProtoSchema> = vec![];
+        for x in result.into_iter() {
+            schemas.push(x?);
+        }
+        Ok(schemas)
+    }
+
+    // the schema of the key
+    pub fn find(key: &str) -> Result<Option<ProtoSchema>, CusError> {
+        sqlite::find_pattern("proto_schemas", key)?
+            .map(Self::first)
+            .transpose()
+    }
+}
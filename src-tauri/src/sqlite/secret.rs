@@ -0,0 +1,222 @@
+use crate::{err::CusError, sqlite};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use rusqlite::{params, Connection as SqliteConnection, OptionalExtension};
+use std::fs;
+use std::sync::Mutex as SMutex;
+
+// the encrypted value is saved as the prefix with hex(nonce + ciphertext)
+const PREFIX: &str = "enc:v1:";
+const NONCE_SIZE: usize = 12;
+// a known text encrypted by the master key, to verify the master password
+const CHECK_TEXT: &str = "tauri-redis";
+
+// the secret columns of connections and ssh hops
+pub const COLUMNS: [(&str, &str); 9] = [
+    ("connections", "password"),
+    ("connections", "ssh_password"),
+    ("connections", "ssh_private_key"),
+    ("connections", "ssh_passphrase"),
+    ("connections", "sentinel_password"),
+    ("connections", "proxy_password"),
+    ("ssh_hops", "password"),
+    ("ssh_hops", "private_key"),
+    ("ssh_hops", "passphrase"),
+];
+
+// the key derived from the master password, none if locked
+static MASTER_KEY: SMutex<Option<[u8; 32]>> = SMutex::new(None);
+
+// the master password, or the content of the key file
+pub fn read_secret(
+    password: Option<String>,
+    key_file: Option<String>,
+) -> Result<Vec<u8>, CusError> {
+    if let Some(path) = key_file.filter(|p| !p.is_empty()) {
+        return Ok(fs::read(path)?);
+    }
+    match password.filter(|p| !p.is_empty()) {
+        Some(p) => Ok(p.into_bytes()),
+        None => Err(CusError::build("Master password or key file is required")),
+    }
+}
+
+pub fn derive_key(secret: &[u8], salt: &[u8]) -> Result<[u8; 32], CusError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| CusError::App(e.to_string()))?;
+    Ok(key)
+}
+
+pub fn encrypt_with(key: &[u8; 32], value: &str) -> Result<String, CusError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut data = nonce.to_vec();
+    let encrypted = cipher
+        .encrypt(&nonce, value.as_bytes())
+        .map_err(|_| CusError::build("Encrypt failed"))?;
+    data.extend(encrypted);
+    Ok(format!("{}{}", PREFIX, hex::encode(data)))
+}
+
+pub fn decrypt_with(key: &[u8; 32], value: &str) -> Result<String, CusError> {
+    let data = match value.strip_prefix(PREFIX).map(hex::decode) {
+        Some(Ok(data)) if data.len() > NONCE_SIZE => data,
+        _ => return Err(CusError::build("Invalid encrypted value")),
+    };
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let (nonce, encrypted) = data.split_at(NONCE_SIZE);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), encrypted)
+        .map_err(|_| CusError::build("Wrong master password"))?;
+    Ok(String::from_utf8(plain)?)
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+fn get_setting(client: &SqliteConnection, key: &str) -> Result<Option<String>, CusError> {
+    let value = client
+        .query_row("select value from settings where key = ?1", [key], |r| {
+            r.get(0)
+        })
+        .optional()?;
+    Ok(value)
+}
+
+fn set_setting(client: &SqliteConnection, key: &str, value: &str) -> Result<(), CusError> {
+    client.execute(
+        "insert or replace into settings(key, value) values(?1, ?2)",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+// the master password is set or not
+pub fn is_enabled() -> Result<bool, CusError> {
+    let client = sqlite::get_client()?;
+    Ok(get_setting(&client, "master_check")?.is_some())
+}
+
+pub fn is_unlocked() -> bool {
+    MASTER_KEY.lock().unwrap().is_some()
+}
+
+pub fn lock() {
+    *MASTER_KEY.lock().unwrap() = None;
+}
+
+// verify the secret and keep the key in memory
+fn verify(client: &SqliteConnection, secret: &[u8]) -> Result<[u8; 32], CusError> {
+    let salt = match get_setting(client, "master_salt")? {
+        Some(s) => hex::decode(s).map_err(|e| CusError::App(e.to_string()))?,
+        None => return Err(CusError::build("Master password is not set")),
+    };
+    let check = get_setting(client, "master_check")?.unwrap_or_default();
+    let key = derive_key(secret, &salt)?;
+    if decrypt_with(&key, &check)? != CHECK_TEXT {
+        return Err(CusError::build("Wrong master password"));
+    }
+    Ok(key)
+}
+
+// encrypt the plaintext columns, and re-encrypt the columns encrypted by the old key
+fn encrypt_rows(
+    client: &SqliteConnection,
+    old_key: Option<&[u8; 32]>,
+    key: &[u8; 32],
+) -> Result<(), CusError> {
+    for (table, column) in COLUMNS {
+        let mut stmt = client.prepare(&format!(
+            "select id, {} from {} where {} is not null and {} != ''",
+            column, table, column, column
+        ))?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<(i64, String)>, _>>()?;
+        for (id, value) in rows {
+            let plain = match (is_encrypted(&value), old_key) {
+                (false, _) => value,
+                (true, Some(old)) => decrypt_with(old, &value)?,
+                (true, None) => continue,
+            };
+            client.execute(
+                &format!("update {} set {} = ?1 where id = ?2", table, column),
+                params!(encrypt_with(key, &plain)?, id),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn save_key(client: &SqliteConnection, secret: &[u8]) -> Result<[u8; 32], CusError> {
+    let salt: [u8; 16] = rand::random();
+    let key = derive_key(secret, &salt)?;
+    set_setting(client, "master_salt", &hex::encode(salt))?;
+    set_setting(client, "master_check", &encrypt_with(&key, CHECK_TEXT)?)?;
+    Ok(key)
+}
+
+// set the master password, the existing plaintext secrets are encrypted
+pub fn setup(secret: &[u8]) -> Result<(), CusError> {
+    let mut client = sqlite::get_client()?;
+    if get_setting(&client, "master_check")?.is_some() {
+        return Err(CusError::build("Master password is already set"));
+    }
+    let tx = client.transaction()?;
+    let key = save_key(&tx, secret)?;
+    encrypt_rows(&tx, None, &key)?;
+    tx.commit()?;
+    *MASTER_KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+// unlock by the master password, the plaintext secrets left are encrypted
+pub fn unlock(secret: &[u8]) -> Result<(), CusError> {
+    let client = sqlite::get_client()?;
+    let key = verify(&client, secret)?;
+    encrypt_rows(&client, None, &key)?;
+    *MASTER_KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+// change the master password, all secrets are re-encrypted by the new key
+pub fn change(old_secret: &[u8], secret: &[u8]) -> Result<(), CusError> {
+    let mut client = sqlite::get_client()?;
+    let old_key = verify(&client, old_secret)?;
+    let tx = client.transaction()?;
+    let key = save_key(&tx, secret)?;
+    encrypt_rows(&tx, Some(&old_key), &key)?;
+    tx.commit()?;
+    *MASTER_KEY.lock().unwrap() = Some(key);
+    Ok(())
+}
+
+// encrypt the value if the master password is set
+pub fn encrypt(value: &Option<String>) -> Result<Option<String>, CusError> {
+    match value {
+        Some(v) if !v.is_empty() && !is_encrypted(v) && is_enabled()? => {
+            match *MASTER_KEY.lock().unwrap() {
+                Some(key) => Ok(Some(encrypt_with(&key, v)?)),
+                None => Err(CusError::locked()),
+            }
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+// decrypt the value, the encrypted value is kept if locked
+pub fn decrypt(value: &Option<String>) -> Result<Option<String>, CusError> {
+    match value {
+        Some(v) if is_encrypted(v) => match *MASTER_KEY.lock().unwrap() {
+            Some(key) => Ok(Some(decrypt_with(&key, v)?)),
+            None => Ok(value.clone()),
+        },
+        _ => Ok(value.clone()),
+    }
+}
@@ -0,0 +1,157 @@
+use rusqlite::{self, params, Connection as SqliteConnection, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::sqlite::secret;
+use crate::{err::CusError, sqlite, ssh};
+
+// the jump host behind the ssh host of the connection, ordered by sort
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SshHop {
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub private_key: Option<String>,
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub agent: bool,
+}
+
+impl SshHop {
+    pub fn build(r: &Row) -> SshHop {
+        SshHop {
+            id: r.get(0).unwrap(),
+            host: r.get(1).unwrap(),
+            port: r.get(2).unwrap_or_default(),
+            username: r.get(3).unwrap_or_default(),
+            password: r.get(4).unwrap_or_default(),
+            private_key: r.get(5).unwrap_or_default(),
+            passphrase: r.get(6).unwrap_or_default(),
+            agent: r.get::<_, i64>(7).unwrap_or_default() > 0,
+        }
+    }
+
+    pub fn get_jump_host(&self) -> ssh::JumpHost {
+        ssh::JumpHost {
+            host: self.host.clone(),
+            port: self.port.unwrap_or(22),
+            username: self.username.clone().unwrap_or_default(),
+            password: self.password.clone(),
+            private_key: self.private_key.clone(),
+            passphrase: self.passphrase.clone(),
+            agent: self.agent,
+        }
+    }
+
+    // decrypt the secret columns, they are kept encrypted if locked
+    pub fn decrypt(&mut self) -> Result<(), CusError> {
+        self.password = secret::decrypt(&self.password)?;
+        self.private_key = secret::decrypt(&self.private_key)?;
+        self.passphrase = secret::decrypt(&self.passphrase)?;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        [&self.password, &self.private_key, &self.passphrase]
+            .iter()
+            .any(|v| v.as_deref().is_some_and(secret::is_encrypted))
+    }
+
+    // the hops of all connections group by the connection id
+    pub fn all() -> Result<HashMap<i64, Vec<SshHop>>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            host,
+            port,
+            username,
+            password,
+            private_key,
+            passphrase,
+            agent,
+            connection_id
+            from ssh_hops order by connection_id, sort",
+        )?;
+        let result = stmt.query_map([], |r| Ok((r.get::<_, i64>(8)?, Self::build(r))))?;
+        let mut hops: HashMap<i64, Vec<SshHop>> = HashMap::new();
+        for x in result.into_iter() {
+            let (id, mut hop) = x?;
+            hop.decrypt()?;
+            hops.entry(id).or_default().push(hop);
+        }
+        Ok(hops)
+    }
+
+    // the hops of the connection
+    pub fn of(connection_id: i64) -> Result<Vec<SshHop>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            host,
+            port,
+            username,
+            password,
+            private_key,
+            passphrase,
+            agent
+            from ssh_hops where connection_id = ?1 order by sort",
+        )?;
+        let result = stmt.query_map([connection_id], |r| Ok(Self::build(r)))?;
+        let mut hops = vec![];
+        for x in result.into_iter() {
+            let mut hop = x?;
+            hop.decrypt()?;
+            hops.push(hop);
+        }
+        Ok(hops)
+    }
+
+    // replace the hops of the connection, the order of the slice is the order of the chain
+    pub fn save(
+        conn: &SqliteConnection,
+        connection_id: i64,
+        hops: &mut [SshHop],
+    ) -> Result<(), CusError> {
+        conn.execute(
+            "delete from ssh_hops where connection_id = ?1",
+            [connection_id],
+        )?;
+        for (sort, hop) in hops.iter_mut().enumerate() {
+            // the key still encrypted while locked is not a path
+            if !hop.private_key.as_deref().is_some_and(secret::is_encrypted) {
+                hop.private_key = ssh::get_private_key(&hop.private_key)?;
+            }
+            conn.execute(
+                "insert into ssh_hops(
+                connection_id,
+                sort,
+                host,
+                port,
+                username,
+                password,
+                private_key,
+                passphrase,
+                agent
+                ) values(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params!(
+                    connection_id,
+                    sort as i64,
+                    &hop.host,
+                    hop.port,
+                    hop.username,
+                    secret::encrypt(&hop.password)?,
+                    secret::encrypt(&hop.private_key)?,
+                    secret::encrypt(&hop.passphrase)?,
+                    hop.agent as i64,
+                ),
+            )?;
+            hop.id = Some(conn.last_insert_rowid());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use rusqlite::{self, params, Connection as SqliteConnection, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    err::CusError,
+    sqlite::{self, Policy},
+};
+
+// the free-form tag of the connection, such as prod or env=prod
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Tag {
+    // parse the tag from name or name=value
+    pub fn parse(s: &str) -> Tag {
+        match s.split_once("=") {
+            Some((name, value)) => Tag {
+                name: name.trim().to_string(),
+                value: Some(value.trim().to_string()),
+            },
+            None => Tag {
+                name: s.trim().to_string(),
+                value: None,
+            },
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match &self.value {
+            Some(value) if !value.is_empty() => format!("{}={}", self.name, value),
+            _ => self.name.clone(),
+        }
+    }
+
+    // the tags of the connection
+    pub fn of(connection_id: i64) -> Result<Vec<Tag>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select name, value from connection_tags where connection_id = ?1 order by id",
+        )?;
+        let result = stmt.query_map([connection_id], |r| {
+            Ok(Tag {
+                name: r.get(0)?,
+                value: r.get(1)?,
+            })
+        })?;
+        let mut tags = vec![];
+        for x in result.into_iter() {
+            tags.push(x?);
+        }
+        Ok(tags)
+    }
+
+    // the tags of all connections group by the connection id
+    pub fn all() -> Result<HashMap<i64, Vec<Tag>>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt =
+            conn.prepare("select connection_id, name, value from connection_tags order by id")?;
+        let result = stmt.query_map([], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                Tag {
+                    name: r.get(1)?,
+                    value: r.get(2)?,
+                },
+            ))
+        })?;
+        let mut tags: HashMap<i64, Vec<Tag>> = HashMap::new();
+        for x in result.into_iter() {
+            let (id, tag) = x?;
+            tags.entry(id).or_default().push(tag);
+        }
+        Ok(tags)
+    }
+
+    // replace the tags of the connection
+    pub fn save(conn: &SqliteConnection, connection_id: i64, tags: &[Tag]) -> Result<(), CusError> {
+        conn.execute(
+            "delete from connection_tags where connection_id = ?1",
+            [connection_id],
+        )?;
+        for tag in tags.iter().filter(|t| !t.name.trim().is_empty()) {
+            conn.execute(
+                "insert into connection_tags(connection_id, name, value) values(?1, ?2, ?3)",
+                params!(connection_id, tag.name.trim(), tag.value),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// the policy applied to the connections with the tag
+// readonly makes the connection readonly, policy is used if the connection has no policy
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TagRule {
+    pub id: Option<i64>,
+    pub tag: String,
+    #[serde(default)]
+    pub readonly: bool,
+    pub policy: Option<String>,
+}
+
+impl TagRule {
+    pub fn build(r: &Row) -> TagRule {
+        TagRule {
+            id: r.get(0).unwrap(),
+            tag: r.get(1).unwrap(),
+            readonly: r.get::<_, i64>(2).unwrap_or_default() > 0,
+            policy: r.get(3).unwrap_or_default(),
+        }
+    }
+
+    // the rule matches the tag by name or name=value, case insensitive
+    pub fn matches(&self, tags: &[Tag]) -> bool {
+        let rule = Tag::parse(&self.tag);
+        tags.iter().any(|t| {
+            t.name.eq_ignore_ascii_case(&rule.name)
+                && match &rule.value {
+                    Some(value) => t
+                        .value
+                        .as_deref()
+                        .is_some_and(|v| v.eq_ignore_ascii_case(value)),
+                    None => true,
+                }
+        })
+    }
+
+    pub fn first(id: u32) -> Result<TagRule, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt = conn.prepare(
+            "select
+            id,
+            tag,
+            readonly,
+            policy
+            from tag_rules where id= ?1",
+        )?;
+        let r = stmt.query_row([id], |r| Ok(Self::build(r)))?;
+        Ok(r)
+    }
+
+    pub fn save(&mut self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        self.tag = Tag::parse(&self.tag).label();
+        Policy::check(&self.policy)?;
+        conn.execute(
+            "insert into tag_rules(
+                tag,
+                readonly,
+                policy
+                ) values(?1, ?2, ?3)",
+            params!(&self.tag, self.readonly as i64, self.policy),
+        )?;
+        self.id = Some(conn.last_insert_rowid());
+        Ok(())
+    }
+
+    pub fn del(self) -> Result<(), CusError> {
+        let conn = sqlite::get_client()?;
+        conn.execute("delete from tag_rules where id = ?1", [self.id])?;
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<TagRule>, CusError> {
+        let conn = sqlite::get_client()?;
+        let mut stmt_result = conn.prepare(
+            "select
+            id,
+            tag,
+            readonly,
+            policy
+            from tag_rules order by tag",
+        )?;
+        let result = stmt_result.query_map([], |row| Ok(Self::build(row)))?;
+        let mut rules: Vec<TagRule> = vec![];
+        for x in result.into_iter() {
+            rules.push(x?);
+        }
+        Ok(rules)
+    }
+}
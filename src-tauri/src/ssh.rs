@@ -1,12 +1,32 @@
 use crate::err::CusError;
+use crate::proxy::{self, ProxyParams};
+use crate::sqlite::KnownHost;
 use crate::utils;
-use ssh_jumper::{
-    model::{HostAddress, HostSocketParams, JumpHostAuthParams, SshForwarderEnd, SshTunnelParams},
-    SshJumper,
+use async_io::Async;
+use async_ssh2_lite::{
+    ssh2::{CheckResult, HashType, HostKeyType, KnownHostFileKind},
+    AsyncChannel, AsyncSession, SessionConfiguration,
 };
-use std::borrow::Cow;
-use std::{net::SocketAddr, path::Path};
-use tokio::sync::oneshot::Receiver;
+use base64::Engine;
+use futures::{future, AsyncReadExt};
+use ssh_jumper::model::SshForwarderEnd;
+use std::fs;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+use tokio::sync::oneshot::{self, Receiver};
+use tokio::task::JoinSet;
+
+// a jump host after the first one
+#[derive(Debug, Clone)]
+pub struct JumpHost {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: Option<String>,
+    pub private_key: Option<String>,
+    pub passphrase: Option<String>,
+    pub agent: bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct SshParams {
@@ -14,67 +34,295 @@ pub struct SshParams {
     pub port: u16,
     pub username: String,
     pub password: Option<String>,
+    // the content of the private key, a file path is still accepted for the old connections
     pub private_key: Option<String>,
     pub passphrase: Option<String>,
+    // authenticate with the identities of ssh-agent
+    pub agent: bool,
+    // the jump hosts behind the first one, each is tunnelled through the previous one
+    pub jumps: Vec<JumpHost>,
+    // the first hop is connected through the proxy
+    pub proxy: Option<ProxyParams>,
     pub target_host: String,
     pub target_port: u16,
+    pub timeout: Option<Duration>,
+}
+
+impl SshParams {
+    // the hops of the chain in order
+    fn hops(&self) -> Vec<JumpHost> {
+        let mut hops = vec![JumpHost {
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            private_key: self.private_key.clone(),
+            passphrase: self.passphrase.clone(),
+            agent: self.agent,
+        }];
+        hops.extend(self.jumps.iter().cloned());
+        hops
+    }
 }
 
 pub trait SshProxy {
-    fn store_addr(&mut self, addr: SocketAddr, rx: Receiver<SshForwarderEnd>);
+    // the local address of the last hop, and a receiver for every hop
+    fn store_addr(&mut self, addr: SocketAddr, rx: Vec<Receiver<SshForwarderEnd>>);
     fn get_ssh_config(&self) -> Option<SshParams>;
     fn close_tunnel(&mut self);
 }
+
+type Session = AsyncSession<TcpStream>;
+
 // create ssh proxy
 pub async fn create_tunnel<T: SshProxy>(t: &mut T) -> Result<Option<()>, CusError> {
     if let Some(config) = t.get_ssh_config() {
-        let mut jump_host = HostAddress::HostName(Cow::Borrowed(&config.host));
-        if let Ok(ip_addr) = utils::string_to_ip(&config.host) {
-            jump_host = HostAddress::IpAddr(ip_addr);
-        }
-        let mut auth: JumpHostAuthParams;
-        let mut password = String::new();
-        if let Some(p) = config.password {
-            password = p;
-        }
-        auth = JumpHostAuthParams::password(
-            Cow::from(config.username.as_str()),
-            Cow::from(password.as_str()),
-        );
-
-        let mut private_key = String::new();
-        if let Some(k) = config.private_key {
-            private_key = k
+        let (addr, rx) = match config.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, open_tunnel(&config)).await {
+                Ok(r) => r?,
+                Err(_) => return Err(CusError::build("SSH Connection Timeout")),
+            },
+            None => open_tunnel(&config).await?,
+        };
+        t.store_addr(addr, rx);
+    }
+    Ok(None)
+}
+
+// open the tunnels hop by hop, the next hop is connected through the local port of the previous one
+// if a hop fails, the opened tunnels are closed by dropping the receivers
+async fn open_tunnel(
+    config: &SshParams,
+) -> Result<(SocketAddr, Vec<Receiver<SshForwarderEnd>>), CusError> {
+    let hops = config.hops();
+    let mut ends = vec![];
+    let mut via = None;
+    for (i, hop) in hops.iter().enumerate() {
+        let (host, port) = match hops.get(i + 1) {
+            Some(next) => (next.host.as_str(), next.port),
+            None => (config.target_host.as_str(), config.target_port),
+        };
+        let session = match via {
+            Some(addr) => open_session(hop, Some(addr), None).await?,
+            None => open_session(hop, None, config.proxy.as_ref()).await?,
+        };
+        let channel = session
+            .channel_direct_tcpip(host, port, None)
+            .await
+            .map_err(|e| ssh_err("Open Tunnel Fail", e))?;
+        let (addr, rx) = forward(session, channel, host.to_string(), port)?;
+        via = Some(addr);
+        ends.push(rx);
+    }
+    match via {
+        Some(addr) => Ok((addr, ends)),
+        None => Err(CusError::build("SSH Host Not Found")),
+    }
+}
+
+// connect to the hop directly, through the local address of the previous hop or the proxy
+async fn open_session(
+    hop: &JumpHost,
+    via: Option<SocketAddr>,
+    proxy: Option<&ProxyParams>,
+) -> Result<Session, CusError> {
+    let stream = match (via, proxy) {
+        (Some(addr), _) => Async::<TcpStream>::connect(addr).await,
+        (None, Some(p)) => {
+            let stream = proxy::connect(p, &hop.host, hop.port).await?;
+            Async::new(stream.into_std()?)
         }
-        let mut passphrase_str = String::new();
-        let mut passphrase = None;
-        if let Some(p) = config.passphrase {
-            passphrase_str = p
+        (None, None) => {
+            let addr = tokio::net::lookup_host((hop.host.as_str(), hop.port))
+                .await?
+                .next()
+                .ok_or_else(|| CusError::build("SSH Host Not Found"))?;
+            Async::<TcpStream>::connect(addr).await
         }
-        if !passphrase_str.is_empty() {
-            passphrase = Some(Cow::from(passphrase_str.as_str()));
+    }
+    .map_err(|e| ssh_err("SSH Connect Fail", e))?;
+    // the later hops are connected through the local port, only the first one crosses the network
+    if via.is_none() {
+        utils::set_keepalive(stream.get_ref())?;
+    }
+    let mut configuration = SessionConfiguration::new();
+    configuration.set_compress(true);
+    let mut session = AsyncSession::new(stream, Some(configuration))?;
+    session
+        .handshake()
+        .await
+        .map_err(|e| ssh_err("SSH Handshake Fail", e))?;
+    verify_host_key(&session, &hop.host, hop.port)?;
+    authenticate(&session, hop)
+        .await
+        .map_err(|e| ssh_err("SSH Authentication Fail", e))?;
+    if !session.authenticated() {
+        return Err(CusError::build("SSH Authentication Fail"));
+    }
+    Ok(session)
+}
+
+fn ssh_err(title: &str, e: std::io::Error) -> CusError {
+    CusError::App(format!("{}: {}", title, e))
+}
+
+// check the host key with ~/.ssh/known_hosts first, then the hosts trusted in the app
+// an unknown host is rejected with its fingerprint, so the user can trust it and retry
+fn verify_host_key(session: &Session, host: &str, port: u16) -> Result<(), CusError> {
+    let (key, key_type) = match session.host_key() {
+        Some(k) => k,
+        None => return Err(CusError::build("SSH Host Key Not Found")),
+    };
+    if let Some(path) = dirs_next::home_dir().map(|h| h.join(".ssh").join("known_hosts")) {
+        if path.exists() {
+            let mut known_hosts = session.known_hosts()?;
+            if known_hosts
+                .read_file(&path, KnownHostFileKind::OpenSSH)
+                .is_ok()
+            {
+                match known_hosts.check_port(host, port, key) {
+                    CheckResult::Match => return Ok(()),
+                    CheckResult::Mismatch => return Err(KnownHost::changed(host, port)),
+                    _ => {}
+                }
+            }
         }
-        if !private_key.is_empty() {
-            auth = JumpHostAuthParams::key_pair(
-                Cow::from(config.username.as_str()),
-                Cow::from(Path::new(private_key.as_str())),
-                passphrase,
-            );
+    }
+    let fingerprint = match session.host_key_hash(HashType::Sha256) {
+        Some(hash) => base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash),
+        None => return Err(CusError::build("SSH Host Key Not Found")),
+    };
+    match KnownHost::find(host, port)? {
+        Some(known) if known.fingerprint == fingerprint => Ok(()),
+        Some(_) => Err(KnownHost::changed(host, port)),
+        None => Err(CusError::HostKey(
+            format!("{}:{}", host, port),
+            key_type_name(key_type).to_string(),
+            fingerprint,
+        )),
+    }
+}
+
+fn key_type_name(t: HostKeyType) -> &'static str {
+    match t {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed255219 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+// authenticate with ssh-agent, the private key or the password
+async fn authenticate(session: &Session, hop: &JumpHost) -> std::io::Result<()> {
+    let username = hop.username.as_str();
+    if hop.agent {
+        return session.userauth_agent_with_try_next(username).await;
+    }
+    let passphrase = hop.passphrase.as_deref().filter(|p| !p.is_empty());
+    match get_private_key(&hop.private_key)? {
+        Some(key) => userauth_key(session, username, &key, passphrase).await,
+        None => {
+            let password = hop.password.clone().unwrap_or_default();
+            session.userauth_password(username, &password).await
         }
-        let mut target_host = HostAddress::HostName(Cow::Borrowed(&config.target_host));
-        if let Ok(ip_addr) = utils::string_to_ip(&config.target_host) {
-            target_host = HostAddress::IpAddr(ip_addr);
+    }
+}
+
+// the private key content, the old connections store the path of the key file
+pub fn get_private_key(key: &Option<String>) -> std::io::Result<Option<String>> {
+    match key.as_deref().map(str::trim) {
+        Some(k) if k.is_empty() => Ok(None),
+        Some(k) if k.starts_with("-----BEGIN") || k.starts_with("PuTTY-User-Key-File") => {
+            Ok(Some(k.to_string()))
         }
-        let target_socket: HostSocketParams<'_> = HostSocketParams {
-            address: target_host,
-            port: config.target_port,
-        };
-        let ssh_params =
-            SshTunnelParams::new(jump_host, auth, target_socket).with_jump_host_port(config.port);
-        match SshJumper::open_tunnel(&ssh_params).await {
-            Ok((addr, rx)) => t.store_addr(addr, rx),
-            Err(e) => return Err(CusError::App(e.to_string())),
+        Some(path) => Ok(Some(fs::read_to_string(path)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(unix)]
+async fn userauth_key(
+    session: &Session,
+    username: &str,
+    key: &str,
+    passphrase: Option<&str>,
+) -> std::io::Result<()> {
+    session
+        .userauth_pubkey_memory(username, None, key, passphrase)
+        .await
+}
+
+// libssh2 only reads the key from memory on unix, so the key is written to a temp file
+#[cfg(not(unix))]
+async fn userauth_key(
+    session: &Session,
+    username: &str,
+    key: &str,
+    passphrase: Option<&str>,
+) -> std::io::Result<()> {
+    let path = std::env::temp_dir().join(crate::utils::random_str(16));
+    fs::write(&path, key)?;
+    let result = session
+        .userauth_pubkey_file(username, None, &path, passphrase)
+        .await;
+    let _ = fs::remove_file(&path);
+    result
+}
+
+// forward a local port to the target, every accepted connection opens a channel on the session
+// the first channel is opened by the caller, so the errors are returned before the port is used
+// the forwarder and its pipes stop when the receiver is closed by SshProxy::close_tunnel
+fn forward(
+    session: Session,
+    channel: AsyncChannel<TcpStream>,
+    host: String,
+    port: u16,
+) -> Result<(SocketAddr, Receiver<SshForwarderEnd>), CusError> {
+    let listener = Async::<TcpListener>::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+    let addr = listener.get_ref().local_addr()?;
+    let (mut tx, rx) = oneshot::channel::<SshForwarderEnd>();
+    tokio::spawn(async move {
+        let mut first = Some(channel);
+        let mut pipes = JoinSet::new();
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        let _ = tx.send(SshForwarderEnd::LocalConnectFail(e));
+                        break;
+                    }
+                },
+                _ = tx.closed() => break,
+            };
+            while pipes.try_join_next().is_some() {}
+            let channel = match first.take() {
+                Some(c) => c,
+                // the local connection is dropped, the client gets the error
+                None => match session.channel_direct_tcpip(&host, port, None).await {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                },
+            };
+            pipes.spawn(pipe(stream, channel));
         }
+    });
+    Ok((addr, rx))
+}
+
+async fn pipe(stream: Async<TcpStream>, channel: AsyncChannel<TcpStream>) -> SshForwarderEnd {
+    let (local_r, mut local_w) = stream.split();
+    let (channel_r, mut channel_w) = channel.split();
+    let upload = futures::io::copy(local_r, &mut channel_w);
+    let download = futures::io::copy(channel_r, &mut local_w);
+    futures::pin_mut!(upload, download);
+    match future::select(upload, download).await {
+        future::Either::Left((Ok(_), _)) => SshForwarderEnd::LocalReadEof,
+        future::Either::Left((Err(e), _)) => SshForwarderEnd::LocalReadErr(e),
+        future::Either::Right((Ok(_), _)) => SshForwarderEnd::ChannelReadEof,
+        future::Either::Right((Err(e), _)) => SshForwarderEnd::ChannelReadErr(e),
     }
-    Ok(None)
 }
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+use std::sync::Mutex as SMutex;
+use tokio::sync::oneshot;
+
+// a state to manager the in-flight dispatch requests
+// each request with an id can be cancelled by the id
+pub struct TaskManager(pub SMutex<HashMap<String, oneshot::Sender<()>>>);
+impl TaskManager {
+    pub fn new() -> TaskManager {
+        TaskManager(SMutex::new(HashMap::new()))
+    }
+    pub fn add(&self, id: String, tx: oneshot::Sender<()>) {
+        self.0.lock().unwrap().insert(id, tx);
+    }
+
+    pub fn remove(&self, id: &String) {
+        self.0.lock().unwrap().remove(id);
+    }
+
+    // return false if the request is finished or not exists
+    pub fn cancel(&self, id: &String) -> bool {
+        if let Some(tx) = self.0.lock().unwrap().remove(id) {
+            return tx.send(()).is_ok();
+        }
+        false
+    }
+}
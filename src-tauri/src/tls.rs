@@ -0,0 +1,108 @@
+use crate::{connection::TlsParams, err::CusError};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot::{self, Receiver};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+fn tls_err(e: impl ToString) -> CusError {
+    CusError::App(format!("TLS Error: {}", e.to_string()))
+}
+
+fn read(path: &Option<String>) -> Result<Option<Vec<u8>>, CusError> {
+    match path {
+        Some(p) if !p.is_empty() => Ok(Some(fs::read(p)?)),
+        _ => Ok(None),
+    }
+}
+
+// the ca file or the system trust store, and the client certificate if it is set
+fn client_config(tls: &TlsParams) -> Result<ClientConfig, CusError> {
+    let mut roots = RootCertStore::empty();
+    match read(&tls.ca)? {
+        Some(pem) => {
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(cert?).map_err(tls_err)?;
+            }
+        }
+        None => {
+            // the system store may have some certificates rustls does not accept
+            for cert in rustls_native_certs::load_native_certs()? {
+                let _ = roots.add(cert);
+            }
+        }
+    }
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(tls_err)?
+            .with_root_certificates(roots);
+    match (read(&tls.cert)?, read(&tls.key)?) {
+        (Some(cert), Some(key)) => {
+            let certs = rustls_pemfile::certs(&mut cert.as_slice()).collect::<Result<_, _>>()?;
+            let key = rustls_pemfile::private_key(&mut key.as_slice())?
+                .ok_or_else(|| CusError::build("No private key in the client key file"))?;
+            builder.with_client_auth_cert(certs, key).map_err(tls_err)
+        }
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err(CusError::build(
+            "Both client certificate and key are required",
+        )),
+    }
+}
+
+async fn connect(
+    connector: &TlsConnector,
+    name: &ServerName<'static>,
+    addr: SocketAddr,
+) -> Result<TlsStream<TcpStream>, CusError> {
+    let stream = TcpStream::connect(addr).await?;
+    connector
+        .connect(name.clone(), stream)
+        .await
+        .map_err(tls_err)
+}
+
+// forward a local port to the tls server at the address, the certificate is checked against the name
+// redis connects to the local port without tls, so the tunnelled or proxied server can be verified
+// the handshake is checked before the port is returned, the bridge stops when the receiver is closed
+pub async fn bridge(
+    tls: &TlsParams,
+    server_name: &str,
+    addr: SocketAddr,
+) -> Result<(SocketAddr, Receiver<()>), CusError> {
+    let connector = TlsConnector::from(Arc::new(client_config(tls)?));
+    let name = ServerName::try_from(server_name.to_string())
+        .map_err(|_| CusError::App(format!("Invalid TLS server name {}", server_name)))?;
+    let mut first = Some(connect(&connector, &name, addr).await?);
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let local_addr = listener.local_addr()?;
+    let (mut tx, rx) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        loop {
+            let mut local = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((local, _)) => local,
+                    Err(_) => break,
+                },
+                _ = tx.closed() => break,
+            };
+            let remote = first.take();
+            let (connector, name) = (connector.clone(), name.clone());
+            tokio::spawn(async move {
+                let remote = match remote {
+                    Some(r) => Ok(r),
+                    None => connect(&connector, &name, addr).await,
+                };
+                if let Ok(mut remote) = remote {
+                    let _ = tokio::io::copy_bidirectional(&mut local, &mut remote).await;
+                }
+            });
+        }
+    });
+    Ok((local_addr, rx))
+}
@@ -2,8 +2,10 @@ use crate::err::CusError;
 use rand::distributions::Alphanumeric;
 use rand::prelude::*;
 
+use socket2::{SockRef, TcpKeepalive};
 use std::net::Ipv6Addr;
 use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
 pub fn compare_version(version1: &str, version2: &str) -> i8 {
     let arr1: Vec<_> = version1.split(".").collect();
@@ -108,3 +110,123 @@ pub fn string_to_ip(s: &str) -> Result<IpAddr, CusError> {
     }
     err
 }
+
+// match the key with the glob pattern the same as redis KEYS
+// * ? [abc] [^a-z] and \ escape are supported
+// a mismatch only goes back to the last star, so the time is at most the product of the lengths
+pub fn glob_match(pattern: &str, key: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let k: Vec<char> = key.chars().collect();
+    let (mut pi, mut ki) = (0, 0);
+    // the pattern after the last star, and the key position the star stops at
+    let mut star: Option<(usize, usize)> = None;
+    while ki < k.len() {
+        if p.get(pi) == Some(&'*') {
+            pi += 1;
+            star = Some((pi, ki));
+            continue;
+        }
+        if let Some(next) = glob_match_one(&p, pi, k[ki]) {
+            pi = next;
+            ki += 1;
+            continue;
+        }
+        // the star takes one more char
+        match star {
+            Some((sp, sk)) => {
+                star = Some((sp, sk + 1));
+                pi = sp;
+                ki = sk + 1;
+            }
+            None => return false,
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+// the position after the pattern item at pi if it matches the char
+fn glob_match_one(p: &[char], pi: usize, c: char) -> Option<usize> {
+    match p.get(pi)? {
+        '*' => None,
+        '?' => Some(pi + 1),
+        '[' => {
+            let mut i = pi + 1;
+            let not = p.get(i) == Some(&'^');
+            if not {
+                i += 1;
+            }
+            let mut matched = false;
+            while i < p.len() && p[i] != ']' {
+                if p[i] == '\\' && i + 1 < p.len() {
+                    matched |= p[i + 1] == c;
+                    i += 2;
+                } else if i + 2 < p.len() && p[i + 1] == '-' && p[i + 2] != ']' {
+                    let (start, end) = (p[i].min(p[i + 2]), p[i].max(p[i + 2]));
+                    matched |= start <= c && c <= end;
+                    i += 3;
+                } else {
+                    matched |= p[i] == c;
+                    i += 1;
+                }
+            }
+            // the unclosed bracket matches to the end of the pattern
+            let next = if i < p.len() { i + 1 } else { i };
+            (matched != not).then_some(next)
+        }
+        '\\' if pi + 1 < p.len() => (p[pi + 1] == c).then_some(pi + 2),
+        x => (*x == c).then_some(pi + 1),
+    }
+}
+
+// the idle time before the tcp keepalive probes of the ssh and proxy sockets
+// the redis crate has no setting for it, its sockets have the keepalive of the system
+const KEEPALIVE: Duration = Duration::from_secs(10);
+
+// enable the tcp keepalive of the socket, a broken ssh or proxy link is found sooner
+pub fn set_keepalive<'a, S>(stream: &'a S) -> std::io::Result<()>
+where
+    SockRef<'a>: From<&'a S>,
+{
+    let keepalive = TcpKeepalive::new()
+        .with_time(KEEPALIVE)
+        .with_interval(KEEPALIVE);
+    SockRef::from(stream).set_tcp_keepalive(&keepalive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("user:*", "user:1"));
+        assert!(glob_match("user:*:name", "user:1:2:name"));
+        assert!(!glob_match("user:*:name", "user:1:names"));
+        assert!(glob_match("h?llo", "hello"));
+        assert!(!glob_match("h?llo", "hllo"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("h[^e]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("h[a-b]llo", "hbllo"));
+        assert!(glob_match("h[b-a]llo", "hallo"));
+        assert!(glob_match("a\\*b", "a*b"));
+        assert!(!glob_match("a\\*b", "axb"));
+        assert!(glob_match("a[\\]]b", "a]b"));
+        assert!(glob_match("a**b*", "ab"));
+        assert!(glob_match("*a*b", "xxaxxbxxb"));
+        assert!(!glob_match("*a*b", "xxaxxbxxc"));
+        assert!(glob_match("键:*", "键:值"));
+        // the unclosed bracket
+        assert!(glob_match("a[bc", "ab"));
+    }
+
+    #[test]
+    fn glob_backtracking() {
+        // the recursive match took exponential time on these
+        let key = "a".repeat(100);
+        assert!(!glob_match(&format!("{}b", "*a".repeat(20)), &key));
+        assert!(glob_match(&"*a".repeat(20), &key));
+    }
+}